@@ -0,0 +1,82 @@
+use std::fs;
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Checks if a cache file is still valid based on its age.
+pub fn is_cache_valid(cache_file: &str, max_age_secs: u64) -> bool {
+    fs::metadata(cache_file)
+        .and_then(|m| m.modified())
+        .map(|modified| {
+            SystemTime::now()
+                .duration_since(modified)
+                .unwrap_or(Duration::from_secs(u64::MAX))
+                < Duration::from_secs(max_age_secs)
+        })
+        .unwrap_or(false)
+}
+
+/// Returns the modification time of a cache file as a Unix timestamp.
+pub fn cache_timestamp(cache_file: &str) -> Result<u64, Box<dyn std::error::Error>> {
+    let modified = fs::metadata(cache_file)?.modified()?;
+    Ok(modified
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::new(0, 0))
+        .as_secs())
+}
+
+/// Atomically writes content to a file using a temporary file and rename.
+/// The temporary file is unique to the process and call, so concurrent writers of the
+/// same cache file don't remove each other's temporary file.
+pub fn atomic_write(file_path: &str, content: &str) -> Result<(), Box<dyn std::error::Error>> {
+    static WRITES: AtomicU64 = AtomicU64::new(0);
+    let temp_file = format!(
+        "{}.{}-{}.tmp",
+        file_path,
+        process::id(),
+        WRITES.fetch_add(1, Ordering::Relaxed)
+    );
+    fs::write(&temp_file, content)?;
+    fs::rename(&temp_file, file_path)?;
+    Ok(())
+}
+
+/// A response body read through the cache.
+pub struct CachedBody {
+    pub text: String,
+    /// Set when the fetch failed and the body comes from an expired cache file.
+    pub stale: bool,
+}
+
+/// Returns the contents of `cache_file` while it is younger than `max_age_secs`,
+/// otherwise calls `fetch` and stores its result. If `fetch` fails, falls back to the
+/// expired cache file (marked stale) and only returns the error when no cache exists.
+pub fn read_through<F>(
+    cache_file: &str,
+    max_age_secs: u64,
+    fetch: F,
+) -> Result<CachedBody, Box<dyn std::error::Error>>
+where
+    F: FnOnce() -> Result<String, Box<dyn std::error::Error>>,
+{
+    if is_cache_valid(cache_file, max_age_secs) {
+        return Ok(CachedBody {
+            text: fs::read_to_string(cache_file)?,
+            stale: false,
+        });
+    }
+
+    match fetch() {
+        Ok(text) => {
+            atomic_write(cache_file, &text)?;
+            Ok(CachedBody { text, stale: false })
+        }
+        Err(err) => match fs::read_to_string(cache_file) {
+            Ok(text) => {
+                eprintln!("{}; using stale cache {}", err, cache_file);
+                Ok(CachedBody { text, stale: true })
+            }
+            Err(_) => Err(err),
+        },
+    }
+}
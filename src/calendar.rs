@@ -0,0 +1,330 @@
+use chrono::prelude::*;
+use chrono::Duration;
+use std::collections::HashMap;
+
+use crate::config::{DstRule, ExchangeConfig};
+
+/// NYSE/NASDAQ full-day closures.
+const NYSE_HOLIDAYS: &[(i32, u32, u32)] = &[
+    (2025, 1, 1),
+    (2025, 1, 9),
+    (2025, 1, 20),
+    (2025, 2, 17),
+    (2025, 4, 18),
+    (2025, 5, 26),
+    (2025, 6, 19),
+    (2025, 7, 4),
+    (2025, 9, 1),
+    (2025, 11, 27),
+    (2025, 12, 25),
+    (2026, 1, 1),
+    (2026, 1, 19),
+    (2026, 2, 16),
+    (2026, 4, 3),
+    (2026, 5, 25),
+    (2026, 6, 19),
+    (2026, 7, 3),
+    (2026, 9, 7),
+    (2026, 11, 26),
+    (2026, 12, 25),
+    (2027, 1, 1),
+    (2027, 1, 18),
+    (2027, 2, 15),
+    (2027, 3, 26),
+    (2027, 5, 31),
+    (2027, 6, 18),
+    (2027, 7, 5),
+    (2027, 9, 6),
+    (2027, 11, 25),
+    (2027, 12, 24),
+    (2028, 1, 17),
+    (2028, 2, 21),
+    (2028, 4, 14),
+    (2028, 5, 29),
+    (2028, 6, 19),
+    (2028, 7, 4),
+    (2028, 9, 4),
+    (2028, 11, 23),
+    (2028, 12, 25),
+];
+
+/// NYSE/NASDAQ early closes (13:00 New York time).
+const NYSE_HALF_DAYS: &[(i32, u32, u32)] = &[
+    (2025, 7, 3),
+    (2025, 11, 28),
+    (2025, 12, 24),
+    (2026, 11, 27),
+    (2026, 12, 24),
+    (2027, 11, 26),
+    (2028, 7, 3),
+    (2028, 11, 24),
+];
+
+/// Regular trading hours and closures of an exchange, in the exchange's own timezone.
+#[derive(Debug, Clone)]
+pub struct ExchangeCalendar {
+    utc_offset_minutes: i32, // Standard (winter) time offset from UTC.
+    dst: DstRule,
+    open: NaiveTime,
+    close: NaiveTime,
+    early_close: NaiveTime,
+    holidays: Vec<NaiveDate>,
+    half_days: Vec<NaiveDate>,
+}
+
+impl ExchangeCalendar {
+    /// The bundled New York Stock Exchange calendar (also used for NASDAQ).
+    pub fn nyse() -> Self {
+        let to_dates = |table: &[(i32, u32, u32)]| {
+            table
+                .iter()
+                .filter_map(|&(y, m, d)| NaiveDate::from_ymd_opt(y, m, d))
+                .collect()
+        };
+        ExchangeCalendar {
+            utc_offset_minutes: -5 * 60,
+            dst: DstRule::Us,
+            open: NaiveTime::from_hms_opt(9, 30, 0).unwrap(),
+            close: NaiveTime::from_hms_opt(16, 0, 0).unwrap(),
+            early_close: NaiveTime::from_hms_opt(13, 0, 0).unwrap(),
+            holidays: to_dates(NYSE_HOLIDAYS),
+            half_days: to_dates(NYSE_HALF_DAYS),
+        }
+    }
+
+    /// Builds a calendar from an `[exchange.<name>]` section.
+    pub fn from_config(name: &str, config: &ExchangeConfig) -> Result<Self, String> {
+        let parse_time = |s: &str| {
+            NaiveTime::parse_from_str(s, "%H:%M")
+                .map_err(|_| format!("Invalid time '{}' for exchange {}, expected HH:MM", s, name))
+        };
+        let parse_dates = |dates: &[String]| {
+            dates
+                .iter()
+                .map(|s| {
+                    NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|_| {
+                        format!(
+                            "Invalid date '{}' for exchange {}, expected YYYY-MM-DD",
+                            s, name
+                        )
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        let close = parse_time(&config.close)?;
+        Ok(ExchangeCalendar {
+            utc_offset_minutes: (config.utc_offset * 60.0).round() as i32,
+            dst: config.dst,
+            open: parse_time(&config.open)?,
+            close,
+            early_close: match &config.early_close {
+                Some(s) => parse_time(s)?,
+                None => close,
+            },
+            holidays: parse_dates(&config.holidays)?,
+            half_days: parse_dates(&config.half_days)?,
+        })
+    }
+
+    /// Looks up an exchange by name: `[exchange.<name>]` sections from the config
+    /// take precedence over the bundled calendars.
+    pub fn lookup(
+        name: &str,
+        configured: &HashMap<String, ExchangeConfig>,
+    ) -> Result<Self, String> {
+        if let Some(config) = configured.get(name) {
+            return Self::from_config(name, config);
+        }
+        match name.to_ascii_uppercase().as_str() {
+            "NYSE" | "NASDAQ" => Ok(Self::nyse()),
+            _ => Err(format!(
+                "Unknown exchange '{}'. Use NYSE, NASDAQ or define an [exchange.{}] section",
+                name, name
+            )),
+        }
+    }
+
+    /// Offset from UTC in effect at the given instant, including daylight saving time.
+    fn offset_at(&self, at: DateTime<Utc>) -> Duration {
+        let standard = Duration::minutes(self.utc_offset_minutes as i64);
+        let year = at.year();
+        let (start, end) = match self.dst {
+            DstRule::None => return standard,
+            // Second Sunday in March 02:00 local standard time until
+            // first Sunday in November 02:00 local daylight time.
+            DstRule::Us => (
+                nth_sunday(year, 3, 2).and_hms_opt(2, 0, 0).unwrap() - standard,
+                nth_sunday(year, 11, 1).and_hms_opt(2, 0, 0).unwrap()
+                    - standard
+                    - Duration::hours(1),
+            ),
+            // Last Sunday in March 01:00 UTC until last Sunday in October 01:00 UTC.
+            DstRule::Eu => (
+                last_sunday(year, 3).and_hms_opt(1, 0, 0).unwrap(),
+                last_sunday(year, 10).and_hms_opt(1, 0, 0).unwrap(),
+            ),
+        };
+        let naive = at.naive_utc();
+        if naive >= start && naive < end {
+            standard + Duration::hours(1)
+        } else {
+            standard
+        }
+    }
+
+    /// Converts an exchange-local date and time to UTC.
+    fn to_utc(&self, date: NaiveDate, time: NaiveTime) -> DateTime<Utc> {
+        let local = date.and_time(time);
+        let guess =
+            Utc.from_utc_datetime(&(local - Duration::minutes(self.utc_offset_minutes as i64)));
+        Utc.from_utc_datetime(&(local - self.offset_at(guess)))
+    }
+
+    /// The exchange-local calendar date at the given instant.
+    fn local_date(&self, at: DateTime<Utc>) -> NaiveDate {
+        (at.naive_utc() + self.offset_at(at)).date()
+    }
+
+    /// Opening and closing instant of the session on `date`, if the exchange trades that day.
+    fn session(&self, date: NaiveDate) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        if date.weekday() == Weekday::Sat
+            || date.weekday() == Weekday::Sun
+            || self.holidays.contains(&date)
+        {
+            return None;
+        }
+        let close = if self.half_days.contains(&date) {
+            self.early_close
+        } else {
+            self.close
+        };
+        Some((self.to_utc(date, self.open), self.to_utc(date, close)))
+    }
+
+    /// Whether the exchange is in its regular session at the given instant.
+    pub fn is_open(&self, at: DateTime<Utc>) -> bool {
+        self.session(self.local_date(at))
+            .map(|(open, close)| at >= open && at < close)
+            .unwrap_or(false)
+    }
+
+    /// The end of the most recent session that closed at or before `at`.
+    pub fn last_close(&self, at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let today = self.local_date(at);
+        (0..30)
+            .filter_map(|days_back| self.session(today - Duration::days(days_back)))
+            .map(|(_, close)| close)
+            .find(|close| *close <= at)
+    }
+
+    /// The start of the next session that opens after `at`.
+    pub fn next_open(&self, at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let today = self.local_date(at);
+        (0..30)
+            .filter_map(|days_ahead| self.session(today + Duration::days(days_ahead)))
+            .map(|(open, _)| open)
+            .find(|open| *open > at)
+    }
+}
+
+/// The `n`th Sunday (1-based) of the given month.
+fn nth_sunday(year: i32, month: u32, n: u32) -> NaiveDate {
+    NaiveDate::from_weekday_of_month_opt(year, month, Weekday::Sun, n as u8).unwrap()
+}
+
+/// The last Sunday of the given month.
+fn last_sunday(year: i32, month: u32) -> NaiveDate {
+    let fifth = NaiveDate::from_weekday_of_month_opt(year, month, Weekday::Sun, 5);
+    fifth.unwrap_or_else(|| nth_sunday(year, month, 4))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+    }
+
+    #[test]
+    fn us_dst_switches_at_two_am_local() {
+        let nyse = ExchangeCalendar::nyse();
+        // 2026: second Sunday in March is the 8th, first Sunday in November the 1st.
+        assert_eq!(nyse.offset_at(utc(2026, 3, 8, 6, 59)), Duration::hours(-5));
+        assert_eq!(nyse.offset_at(utc(2026, 3, 8, 7, 0)), Duration::hours(-4));
+        assert_eq!(nyse.offset_at(utc(2026, 11, 1, 5, 59)), Duration::hours(-4));
+        assert_eq!(nyse.offset_at(utc(2026, 11, 1, 6, 0)), Duration::hours(-5));
+    }
+
+    #[test]
+    fn sessions_follow_dst() {
+        let nyse = ExchangeCalendar::nyse();
+        // Friday before and Monday after the March switch: 09:30 EST, then 09:30 EDT.
+        assert!(!nyse.is_open(utc(2026, 3, 6, 14, 29)));
+        assert!(nyse.is_open(utc(2026, 3, 6, 14, 30)));
+        assert!(!nyse.is_open(utc(2026, 3, 9, 13, 29)));
+        assert!(nyse.is_open(utc(2026, 3, 9, 13, 30)));
+        // Monday after the November switch closes at 16:00 EST.
+        assert!(nyse.is_open(utc(2026, 11, 2, 20, 59)));
+        assert!(!nyse.is_open(utc(2026, 11, 2, 21, 0)));
+    }
+
+    #[test]
+    fn eu_dst_switches_at_one_am_utc() {
+        let xetra = ExchangeCalendar {
+            utc_offset_minutes: 60,
+            dst: DstRule::Eu,
+            ..ExchangeCalendar::nyse()
+        };
+        // 2026: last Sundays are March 29 and October 25.
+        assert_eq!(xetra.offset_at(utc(2026, 3, 29, 0, 59)), Duration::hours(1));
+        assert_eq!(xetra.offset_at(utc(2026, 3, 29, 1, 0)), Duration::hours(2));
+        assert_eq!(
+            xetra.offset_at(utc(2026, 10, 25, 0, 59)),
+            Duration::hours(2)
+        );
+        assert_eq!(xetra.offset_at(utc(2026, 10, 25, 1, 0)), Duration::hours(1));
+    }
+
+    #[test]
+    fn half_days_close_at_one_pm() {
+        let nyse = ExchangeCalendar::nyse();
+        // Day after Thanksgiving 2026, 13:00 EST.
+        assert!(nyse.is_open(utc(2026, 11, 27, 17, 59)));
+        assert!(!nyse.is_open(utc(2026, 11, 27, 18, 0)));
+        assert_eq!(
+            nyse.last_close(utc(2026, 11, 27, 20, 0)),
+            Some(utc(2026, 11, 27, 18, 0))
+        );
+        // Monday 2028-07-03 before Independence Day, 13:00 EDT.
+        assert!(nyse.is_open(utc(2028, 7, 3, 16, 59)));
+        assert!(!nyse.is_open(utc(2028, 7, 3, 17, 0)));
+    }
+
+    #[test]
+    fn observed_holidays_are_closed() {
+        let nyse = ExchangeCalendar::nyse();
+        // Christmas 2027 falls on a Saturday and is observed on Friday the 24th.
+        assert!(!nyse.is_open(utc(2027, 12, 24, 15, 0)));
+        assert_eq!(
+            nyse.next_open(utc(2027, 12, 23, 22, 0)),
+            Some(utc(2027, 12, 27, 14, 30))
+        );
+        // July 4th 2028 is a Tuesday, after the half day on the 3rd.
+        assert!(!nyse.is_open(utc(2028, 7, 4, 15, 0)));
+        assert_eq!(
+            nyse.next_open(utc(2028, 7, 3, 17, 0)),
+            Some(utc(2028, 7, 5, 13, 30))
+        );
+    }
+
+    #[test]
+    fn weekends_span_from_friday_close_to_monday_open() {
+        let nyse = ExchangeCalendar::nyse();
+        let saturday = utc(2026, 10, 17, 12, 0);
+        assert!(!nyse.is_open(saturday));
+        assert_eq!(nyse.last_close(saturday), Some(utc(2026, 10, 16, 20, 0)));
+        assert_eq!(nyse.next_open(saturday), Some(utc(2026, 10, 19, 13, 30)));
+    }
+}
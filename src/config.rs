@@ -0,0 +1,799 @@
+use serde::de::{self, Deserializer, Visitor};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+
+use crate::locale::{self, Locale};
+use crate::quote;
+use crate::template;
+
+/// Global thresholds used for both stocks and crypto.
+#[derive(Debug, Deserialize)]
+pub struct Thresholds {
+    pub critdown: f64, // if percentage change < critdown then "critdown"
+    pub down: f64,     // if percentage change < down (but >= critdown) then "down"
+    pub wayup: f64,    // if percentage change > wayup then "wayup"
+    #[serde(default)]
+    pub up_color: Option<String>,
+    #[serde(default)]
+    pub wayup_color: Option<String>,
+    #[serde(default)]
+    pub down_color: Option<String>,
+    #[serde(default)]
+    pub waydown_color: Option<String>,
+}
+
+/// Stock (Tiingo) provider settings.
+/// `tickers` is the legacy instrument list and is translated into `[[instrument]]` entries.
+#[derive(Debug, Deserialize)]
+pub struct StockConfig {
+    #[serde(default)]
+    pub tickers: Vec<String>,
+    #[serde(default = "default_stock_cache_max_age")]
+    pub cache_max_age: u64, // Cache age while the exchange is in session.
+    #[serde(default = "default_exchange")]
+    pub exchange: String, // Calendar deciding when the market is closed.
+    #[serde(default = "default_settle_minutes")]
+    pub settle_minutes: u64, // Keep cache_max_age this long after the close, until prices are final.
+    #[serde(default)]
+    pub weekend_cache_max_age: Option<u64>, // Deprecated: the exchange calendar decides.
+    #[serde(default = "default_stock_max_concurrent")]
+    pub max_concurrent: usize, // Parallel Tiingo requests.
+}
+
+impl Default for StockConfig {
+    fn default() -> Self {
+        StockConfig {
+            tickers: Vec::new(),
+            cache_max_age: default_stock_cache_max_age(),
+            exchange: default_exchange(),
+            settle_minutes: default_settle_minutes(),
+            weekend_cache_max_age: None,
+            max_concurrent: default_stock_max_concurrent(),
+        }
+    }
+}
+
+/// Crypto (Kraken) provider settings.
+/// `trade_pairs` and `trade_signs` are the legacy instrument lists and are translated
+/// into `[[instrument]]` entries.
+#[derive(Debug, Deserialize)]
+pub struct CryptoConfig {
+    #[serde(default)]
+    pub trade_pairs: Vec<String>,
+    #[serde(default)]
+    pub trade_signs: Vec<String>,
+    #[serde(default = "default_chart_interval")]
+    pub chart_interval: u64,
+    #[serde(default = "default_crypto_cache_max_age")]
+    pub cache_max_age: u64, // Cache age (in seconds) for crypto data.
+    #[serde(default)]
+    pub reference: ReferenceMode, // Price the change is measured against.
+    #[serde(default = "default_crypto_max_concurrent")]
+    pub max_concurrent: usize, // Parallel Kraken requests.
+}
+
+impl Default for CryptoConfig {
+    fn default() -> Self {
+        CryptoConfig {
+            trade_pairs: Vec::new(),
+            trade_signs: Vec::new(),
+            chart_interval: default_chart_interval(),
+            cache_max_age: default_crypto_cache_max_age(),
+            reference: ReferenceMode::default(),
+            max_concurrent: default_crypto_max_concurrent(),
+        }
+    }
+}
+
+/// Price a crypto quote's change is measured against.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReferenceMode {
+    /// The price exactly 24 hours ago, interpolated from the OHLC candles.
+    #[default]
+    Rolling24h,
+    /// Kraken's opening price of the current UTC day.
+    Open,
+    /// The close of the last candle before 00:00 UTC.
+    UtcMidnight,
+    /// The price the given number of hours ago, interpolated like `Rolling24h`.
+    HoursAgo(u64),
+}
+
+impl<'de> Deserialize<'de> for ReferenceMode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ReferenceModeVisitor;
+
+        impl Visitor<'_> for ReferenceModeVisitor {
+            type Value = ReferenceMode;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("\"rolling_24h\", \"open\", \"utc_midnight\" or a number of hours")
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<ReferenceMode, E> {
+                u64::try_from(v)
+                    .map_err(|_| E::custom("reference hours must be positive"))
+                    .and_then(|hours| self.visit_u64(hours))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<ReferenceMode, E> {
+                if v == 0 {
+                    Err(E::custom("reference hours must be positive"))
+                } else {
+                    Ok(ReferenceMode::HoursAgo(v))
+                }
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<ReferenceMode, E> {
+                match v.to_ascii_lowercase().as_str() {
+                    "rolling_24h" => Ok(ReferenceMode::Rolling24h),
+                    "open" => Ok(ReferenceMode::Open),
+                    "utc_midnight" => Ok(ReferenceMode::UtcMidnight),
+                    _ => Err(E::invalid_value(de::Unexpected::Str(v), &self)),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(ReferenceModeVisitor)
+    }
+}
+
+fn default_stock_cache_max_age() -> u64 {
+    60
+}
+
+fn default_exchange() -> String {
+    "NYSE".to_string()
+}
+
+fn default_settle_minutes() -> u64 {
+    15
+}
+
+fn default_stock_max_concurrent() -> usize {
+    4
+}
+
+fn default_chart_interval() -> u64 {
+    5
+}
+
+fn default_crypto_cache_max_age() -> u64 {
+    120
+}
+
+fn default_crypto_max_concurrent() -> usize {
+    2
+}
+
+/// The data source an instrument is fetched from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    Tiingo,
+    Kraken,
+}
+
+impl ProviderKind {
+    /// The market category used by the `--stock` / `--crypto` filters.
+    pub fn category(&self) -> &'static str {
+        match self {
+            ProviderKind::Tiingo => "stock",
+            ProviderKind::Kraken => "crypto",
+        }
+    }
+}
+
+/// A single `[[instrument]]` entry: which provider to ask, what to ask for,
+/// how to display it, and optional per-instrument overrides of provider settings.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Instrument {
+    pub provider: ProviderKind,
+    pub symbol: String,
+    #[serde(default)]
+    pub label: String, // Display name; falls back to the symbol.
+    #[serde(default)]
+    pub icon: String, // Shown instead of the label when set (e.g. "₿").
+    #[serde(default)]
+    pub decimals: Option<Decimals>, // Overrides [format] decimals.
+    #[serde(default)]
+    pub compact: Option<bool>, // Overrides [format] compact.
+    #[serde(default)]
+    pub currency: Option<String>, // ISO code overriding the provider's currency.
+    #[serde(default)]
+    pub cache_max_age: Option<u64>,
+    #[serde(default)]
+    pub exchange: Option<String>, // Tiingo only.
+    #[serde(default)]
+    pub weekend_cache_max_age: Option<u64>, // Deprecated: the exchange calendar decides.
+    #[serde(default)]
+    pub chart_interval: Option<u64>, // Kraken only.
+    #[serde(default)]
+    pub reference: Option<ReferenceMode>, // Kraken only; overrides [crypto] reference.
+    #[serde(default)]
+    pub quantity: Option<f64>, // Units held; makes the instrument part of the portfolio.
+    #[serde(default)]
+    pub cost_basis: Option<f64>, // Price paid per unit, in the instrument's currency.
+    #[serde(default)]
+    pub text: Option<String>, // Overrides [format] text.
+    #[serde(default)]
+    pub tooltip: Option<String>, // Overrides [format] tooltip.
+    #[serde(default)]
+    pub ticker: Option<String>, // Overrides [format] ticker.
+}
+
+impl Instrument {
+    /// Builds an instrument with default display settings and no overrides.
+    fn new(provider: ProviderKind, symbol: &str, icon: &str) -> Self {
+        Instrument {
+            provider,
+            symbol: symbol.to_string(),
+            label: String::new(),
+            icon: icon.to_string(),
+            decimals: None,
+            compact: None,
+            currency: None,
+            cache_max_age: None,
+            exchange: None,
+            weekend_cache_max_age: None,
+            chart_interval: None,
+            reference: None,
+            quantity: None,
+            cost_basis: None,
+            text: None,
+            tooltip: None,
+            ticker: None,
+        }
+    }
+
+    /// The name shown in the bar: the icon if set, otherwise the label, otherwise the symbol.
+    pub fn display_name(&self) -> &str {
+        if !self.icon.is_empty() {
+            &self.icon
+        } else if !self.label.is_empty() {
+            &self.label
+        } else {
+            &self.symbol
+        }
+    }
+}
+
+/// Daylight saving time rule of an exchange's timezone.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DstRule {
+    #[default]
+    None,
+    Us, // Second Sunday in March until first Sunday in November.
+    Eu, // Last Sunday in March until last Sunday in October.
+}
+
+/// An `[exchange.<name>]` section describing trading hours and closures.
+#[derive(Debug, Deserialize)]
+pub struct ExchangeConfig {
+    pub utc_offset: f64, // Standard time offset from UTC in hours, e.g. -5 for New York.
+    #[serde(default)]
+    pub dst: DstRule,
+    pub open: String,  // Local opening time, "HH:MM".
+    pub close: String, // Local closing time, "HH:MM".
+    #[serde(default)]
+    pub early_close: Option<String>, // Closing time on half days.
+    #[serde(default)]
+    pub holidays: Vec<String>, // Full-day closures, "YYYY-MM-DD".
+    #[serde(default)]
+    pub half_days: Vec<String>, // Early-close days, "YYYY-MM-DD".
+}
+
+/// How the currency of a price is shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CurrencyDisplay {
+    /// The currency symbol, e.g. "$123.45" or "123,45 zł".
+    #[default]
+    Symbol,
+    /// The ISO code after the amount, e.g. "123.45 USD".
+    Code,
+    /// The bare amount.
+    None,
+}
+
+/// Output protocol and color markup of the status bar.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// One `{text, tooltip, class}` JSON object per line; the ticker uses Pango markup.
+    #[default]
+    Waybar,
+    /// The i3bar/swaybar protocol: a header followed by an infinite JSON array.
+    #[serde(alias = "swaybar")]
+    I3bar,
+    /// Plain lines with `%{F#rrggbb}` color tags.
+    Polybar,
+    /// Like polybar, for lemonbar.
+    Lemonbar,
+    /// Plain lines with `<fc=#rrggbb>` color tags.
+    Xmobar,
+    /// Plain lines without colors.
+    Plain,
+    /// Plain lines with `#[fg=#rrggbb]` style sequences for the tmux status line.
+    Tmux,
+    /// Plain lines with ANSI terminal colors.
+    Ansi,
+}
+
+impl OutputFormat {
+    /// Parses the value of the `--format` flag.
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "waybar" => Ok(OutputFormat::Waybar),
+            "i3bar" | "swaybar" => Ok(OutputFormat::I3bar),
+            "polybar" => Ok(OutputFormat::Polybar),
+            "lemonbar" => Ok(OutputFormat::Lemonbar),
+            "xmobar" => Ok(OutputFormat::Xmobar),
+            "plain" => Ok(OutputFormat::Plain),
+            "tmux" => Ok(OutputFormat::Tmux),
+            "ansi" => Ok(OutputFormat::Ansi),
+            _ => Err(format!(
+                "Unknown output format '{}', expected waybar, i3bar, polybar, lemonbar, xmobar, plain, tmux or ansi",
+                name
+            )),
+        }
+    }
+}
+
+/// Number of decimals shown for a price: a fixed count, or `"auto"` to pick
+/// enough decimals for the configured significant figures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decimals {
+    Fixed(usize),
+    Auto,
+}
+
+impl<'de> Deserialize<'de> for Decimals {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct DecimalsVisitor;
+
+        impl Visitor<'_> for DecimalsVisitor {
+            type Value = Decimals;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a number of decimals or \"auto\"")
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Decimals, E> {
+                usize::try_from(v)
+                    .map(Decimals::Fixed)
+                    .map_err(|_| E::custom("decimals must not be negative"))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Decimals, E> {
+                Ok(Decimals::Fixed(v as usize))
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Decimals, E> {
+                if v.eq_ignore_ascii_case("auto") {
+                    Ok(Decimals::Auto)
+                } else {
+                    Err(E::invalid_value(de::Unexpected::Str(v), &self))
+                }
+            }
+        }
+
+        deserializer.deserialize_any(DecimalsVisitor)
+    }
+}
+
+/// Output formatting settings shared by all instruments.
+#[derive(Debug, Deserialize)]
+pub struct FormatConfig {
+    #[serde(default)]
+    pub output: OutputFormat, // Overridden by the --format flag.
+    #[serde(default)]
+    pub currency_display: CurrencyDisplay,
+    #[serde(default = "default_decimals")]
+    pub decimals: Decimals, // Default for instruments without their own setting.
+    #[serde(default = "default_significant_figures")]
+    pub significant_figures: usize, // Used by decimals = "auto".
+    #[serde(default)]
+    pub thousands_separator: bool, // Group digits, e.g. 712,345.67.
+    #[serde(default)]
+    pub compact: bool, // Shorten large prices, e.g. 712.3k.
+    #[serde(default)]
+    pub locale: Option<String>, // e.g. "de_DE", or "auto" to read LC_NUMERIC / LANG.
+    #[serde(skip)]
+    pub number_locale: Locale, // Rules resolved from `locale` when the config is loaded.
+    #[serde(default)]
+    pub text: Option<String>, // Template for the bar text, e.g. "{name} {price} ({change_pct})".
+    #[serde(default)]
+    pub tooltip: Option<String>, // Template replacing the built-in tooltip.
+    #[serde(default)]
+    pub ticker: Option<String>, // Template for ticker items; defaults to the text template.
+    #[serde(default)]
+    pub overview: bool, // Tooltip: a table of all instruments instead of the current one.
+}
+
+impl Default for FormatConfig {
+    fn default() -> Self {
+        FormatConfig {
+            output: OutputFormat::default(),
+            currency_display: CurrencyDisplay::default(),
+            decimals: default_decimals(),
+            significant_figures: default_significant_figures(),
+            thousands_separator: false,
+            compact: false,
+            locale: None,
+            number_locale: Locale::default(),
+            text: None,
+            tooltip: None,
+            ticker: None,
+            overview: false,
+        }
+    }
+}
+
+fn default_decimals() -> Decimals {
+    Decimals::Fixed(2)
+}
+
+fn default_significant_figures() -> usize {
+    4
+}
+
+/// Source of the exchange rates used for currency conversion.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FxProvider {
+    #[default]
+    Kraken, // Fiat and crypto pairs, e.g. EURUSD or XBTEUR.
+    Tiingo, // Tiingo FX top-of-book prices.
+    Static, // The `rates` table below.
+}
+
+/// Currency conversion settings (`[fx]`).
+#[derive(Debug, Deserialize)]
+pub struct FxConfig {
+    #[serde(default)]
+    pub display_currency: Option<String>, // Convert every quote into this currency.
+    #[serde(default)]
+    pub provider: FxProvider,
+    #[serde(default = "default_fx_cache_max_age")]
+    pub cache_max_age: u64, // Cache age (in seconds) for exchange rates.
+    #[serde(default)]
+    pub rates: HashMap<String, f64>, // Static rates: value of one unit in the display currency.
+}
+
+impl Default for FxConfig {
+    fn default() -> Self {
+        FxConfig {
+            display_currency: None,
+            provider: FxProvider::default(),
+            cache_max_age: default_fx_cache_max_age(),
+            rates: HashMap::new(),
+        }
+    }
+}
+
+fn default_fx_cache_max_age() -> u64 {
+    3600
+}
+
+/// A period over which additional performance is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Period {
+    #[serde(rename = "1W", alias = "1w")]
+    Week,
+    #[serde(rename = "1M", alias = "1m")]
+    Month,
+    #[serde(rename = "YTD", alias = "ytd")]
+    YearToDate,
+    #[serde(rename = "1Y", alias = "1y")]
+    Year,
+}
+
+impl Period {
+    /// Short name shown in the tooltip, e.g. "1W".
+    pub fn label(self) -> &'static str {
+        match self {
+            Period::Week => "1W",
+            Period::Month => "1M",
+            Period::YearToDate => "YTD",
+            Period::Year => "1Y",
+        }
+    }
+}
+
+/// Multi-period performance settings (`[performance]`).
+#[derive(Debug, Deserialize)]
+pub struct PerformanceConfig {
+    #[serde(default)]
+    pub periods: Vec<Period>, // e.g. ["1W", "1M", "YTD", "1Y"]; empty disables the daily fetch.
+    #[serde(default = "default_performance_cache_max_age")]
+    pub cache_max_age: u64, // Cache age (in seconds) for daily prices.
+}
+
+impl Default for PerformanceConfig {
+    fn default() -> Self {
+        PerformanceConfig {
+            periods: Vec::new(),
+            cache_max_age: default_performance_cache_max_age(),
+        }
+    }
+}
+
+fn default_performance_cache_max_age() -> u64 {
+    6 * 3600
+}
+
+/// Settings of the synthetic portfolio entry (`[portfolio]`).
+#[derive(Debug, Deserialize)]
+pub struct PortfolioConfig {
+    #[serde(default = "default_true")]
+    pub show: bool, // Add the entry to the rotation and ticker when any instrument has a quantity.
+    #[serde(default = "default_portfolio_label")]
+    pub label: String,
+    #[serde(default)]
+    pub currency: Option<String>, // Defaults to [fx] display_currency, then the first holding's.
+}
+
+impl Default for PortfolioConfig {
+    fn default() -> Self {
+        PortfolioConfig {
+            show: true,
+            label: default_portfolio_label(),
+            currency: None,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_portfolio_label() -> String {
+    "Portfolio".to_string()
+}
+
+/// What the Waybar `alt` field carries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WaybarAlt {
+    /// The CSS class, e.g. "down" or "up-stale".
+    #[default]
+    Class,
+    /// The instrument symbol, or "portfolio" for the portfolio entry.
+    Symbol,
+}
+
+/// Extra fields of the Waybar JSON output (`[waybar]`).
+#[derive(Debug, Deserialize)]
+pub struct WaybarConfig {
+    #[serde(default)]
+    pub alt: WaybarAlt,
+    #[serde(default = "default_percentage_min")]
+    pub percentage_min: f64, // Change in percent shown as percentage 0.
+    #[serde(default = "default_percentage_max")]
+    pub percentage_max: f64, // Change in percent shown as percentage 100.
+}
+
+impl Default for WaybarConfig {
+    fn default() -> Self {
+        WaybarConfig {
+            alt: WaybarAlt::default(),
+            percentage_min: default_percentage_min(),
+            percentage_max: default_percentage_max(),
+        }
+    }
+}
+
+fn default_percentage_min() -> f64 {
+    -5.0
+}
+
+fn default_percentage_max() -> f64 {
+    5.0
+}
+
+/// Direction the ticker text moves in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScrollDirection {
+    /// Text enters on the right and moves left, like a news ticker.
+    #[default]
+    Left,
+    Right,
+}
+
+/// How a ticker that fits into its window is placed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StaticAlign {
+    /// Shown at its own width, without padding.
+    #[default]
+    None,
+    /// Padded with spaces to the window size.
+    Left,
+    Center,
+    Right,
+}
+
+/// Ticker mode configuration.
+#[derive(Debug, Deserialize)]
+pub struct TickerConfig {
+    pub window_size: usize,   // Width in display columns
+    pub separator: String,    // Separator between items (e.g., " - ")
+    pub refresh_seconds: u64, // How often to refresh data from APIs
+    #[serde(default = "default_scroll_interval_ms")]
+    pub scroll_interval_ms: u64, // Time between two scroll steps.
+    #[serde(default = "default_scroll_step")]
+    pub step: usize, // Characters moved per scroll step.
+    #[serde(default)]
+    pub direction: ScrollDirection,
+    #[serde(default)]
+    pub pause_ms: u64, // Hold the ticker when an item reaches the edge; 0 disables.
+    #[serde(default)]
+    pub static_align: StaticAlign, // Placement when everything fits and nothing scrolls.
+}
+
+fn default_scroll_interval_ms() -> u64 {
+    1000
+}
+
+fn default_scroll_step() -> usize {
+    1
+}
+
+/// Top-level configuration.
+#[derive(Debug, Deserialize)]
+pub struct Config {
+    pub rotation_seconds: u64, // Global rotation interval for the combined list.
+    #[serde(default)]
+    pub stock: StockConfig,
+    pub thresholds: Thresholds,
+    #[serde(default)]
+    pub crypto: CryptoConfig,
+    pub ticker: Option<TickerConfig>,
+    #[serde(default)]
+    pub format: FormatConfig,
+    #[serde(default)]
+    pub fx: FxConfig,
+    #[serde(default)]
+    pub performance: PerformanceConfig,
+    #[serde(default)]
+    pub portfolio: PortfolioConfig,
+    #[serde(default)]
+    pub waybar: WaybarConfig,
+    #[serde(default, rename = "instrument")]
+    pub instruments: Vec<Instrument>,
+    #[serde(default, rename = "exchange")]
+    pub exchanges: HashMap<String, ExchangeConfig>,
+}
+
+impl Config {
+    /// Reads and parses the configuration file, translating the legacy
+    /// `[stock].tickers` and `[crypto].trade_pairs` lists into instruments.
+    pub fn load(config_file: &str) -> Result<Config, Box<dyn std::error::Error>> {
+        let config_contents = fs::read_to_string(config_file).map_err(|err| {
+            eprintln!(
+                "Error: Could not read config file '{}': {}",
+                config_file, err
+            );
+            err
+        })?;
+        let mut config: Config = toml::from_str(&config_contents).map_err(|err| {
+            eprintln!(
+                "Error: Could not parse config file '{}': {}",
+                config_file, err
+            );
+            err
+        })?;
+
+        config.format.number_locale = locale::resolve(config.format.locale.as_deref());
+        config.translate_legacy_instruments();
+        config.warn_deprecated_settings();
+        config.validate_templates().map_err(|err| {
+            eprintln!(
+                "Error: Invalid template in config file '{}': {}",
+                config_file, err
+            );
+            err
+        })?;
+        config.validate_settings().map_err(|err| {
+            eprintln!(
+                "Error: Invalid settings in config file '{}': {}",
+                config_file, err
+            );
+            err
+        })?;
+        Ok(config)
+    }
+
+    /// Checks settings whose values depend on each other or must not be zero.
+    fn validate_settings(&self) -> Result<(), String> {
+        if self.waybar.percentage_min >= self.waybar.percentage_max {
+            return Err("[waybar] percentage_min must be below percentage_max".to_string());
+        }
+        if self.stock.max_concurrent == 0 || self.crypto.max_concurrent == 0 {
+            return Err("[stock] and [crypto] max_concurrent must be at least 1".to_string());
+        }
+        if let Some(ticker) = &self.ticker {
+            if ticker.refresh_seconds == 0 || ticker.scroll_interval_ms == 0 || ticker.step == 0 {
+                return Err(
+                    "[ticker] refresh_seconds, scroll_interval_ms and step must be at least 1"
+                        .to_string(),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks every `text`, `tooltip` and `ticker` template for syntax errors
+    /// and unknown placeholders.
+    fn validate_templates(&self) -> Result<(), String> {
+        let format = &self.format;
+        let global = [
+            ("[format] text", &format.text),
+            ("[format] tooltip", &format.tooltip),
+            ("[format] ticker", &format.ticker),
+        ];
+        for (place, template) in global {
+            if let Some(template) = template {
+                template::validate(template, quote::PLACEHOLDERS)
+                    .map_err(|err| format!("{}: {}", place, err))?;
+            }
+        }
+        for instrument in &self.instruments {
+            for (field, template) in [
+                ("text", &instrument.text),
+                ("tooltip", &instrument.tooltip),
+                ("ticker", &instrument.ticker),
+            ] {
+                if let Some(template) = template {
+                    template::validate(template, quote::PLACEHOLDERS)
+                        .map_err(|err| format!("{} of {}: {}", field, instrument.symbol, err))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Warns about settings that are still accepted but no longer have any effect.
+    fn warn_deprecated_settings(&self) {
+        let ignored =
+            "is no longer used; the cache is kept while the exchange is closed (see [stock] exchange)";
+        if self.stock.weekend_cache_max_age.is_some() {
+            eprintln!("Warning: [stock] weekend_cache_max_age {}", ignored);
+        }
+        for instrument in &self.instruments {
+            if instrument.weekend_cache_max_age.is_some() {
+                eprintln!(
+                    "Warning: weekend_cache_max_age of instrument {} {}",
+                    instrument.symbol, ignored
+                );
+            }
+        }
+    }
+
+    /// Appends instruments declared in the old `[stock]` and `[crypto]` list form.
+    fn translate_legacy_instruments(&mut self) {
+        for ticker in &self.stock.tickers {
+            self.instruments
+                .push(Instrument::new(ProviderKind::Tiingo, ticker, ""));
+        }
+
+        let crypto = &self.crypto;
+        if !crypto.trade_signs.is_empty() && crypto.trade_signs.len() != crypto.trade_pairs.len() {
+            eprintln!(
+                "Warning: [crypto] has {} trade_pairs but {} trade_signs; use [[instrument]] entries to set icons per pair",
+                crypto.trade_pairs.len(),
+                crypto.trade_signs.len()
+            );
+        }
+        for (i, pair) in crypto.trade_pairs.iter().enumerate() {
+            let sign = crypto.trade_signs.get(i).map(|s| s.as_str()).unwrap_or("");
+            self.instruments
+                .push(Instrument::new(ProviderKind::Kraken, pair, sign));
+        }
+    }
+}
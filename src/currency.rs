@@ -0,0 +1,109 @@
+use crate::config::CurrencyDisplay;
+
+/// Where a currency symbol goes relative to the amount.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    /// Directly before the amount: "$123".
+    Before,
+    /// Before the amount, separated by a space: "CHF 123".
+    BeforeSpaced,
+    /// After the amount, separated by a space: "123 kr".
+    AfterSpaced,
+}
+
+/// Display symbol and placement of a currency.
+#[derive(Debug, Clone, Copy)]
+pub struct CurrencyInfo {
+    pub symbol: &'static str,
+    pub placement: Placement,
+}
+
+/// Currency symbols and their customary placement in English-language formatting.
+const CURRENCIES: &[(&str, &str, Placement)] = &[
+    ("USD", "$", Placement::Before),
+    ("EUR", "€", Placement::Before),
+    ("GBP", "£", Placement::Before),
+    ("JPY", "¥", Placement::Before),
+    ("CNY", "CN¥", Placement::Before),
+    ("CHF", "CHF", Placement::BeforeSpaced),
+    ("CAD", "CA$", Placement::Before),
+    ("AUD", "A$", Placement::Before),
+    ("NZD", "NZ$", Placement::Before),
+    ("HKD", "HK$", Placement::Before),
+    ("SGD", "S$", Placement::Before),
+    ("MXN", "MX$", Placement::Before),
+    ("BRL", "R$", Placement::Before),
+    ("INR", "₹", Placement::Before),
+    ("KRW", "₩", Placement::Before),
+    ("ILS", "₪", Placement::Before),
+    ("TRY", "₺", Placement::Before),
+    ("UAH", "₴", Placement::Before),
+    ("ZAR", "R", Placement::BeforeSpaced),
+    ("RUB", "₽", Placement::AfterSpaced),
+    ("PLN", "zł", Placement::AfterSpaced),
+    ("CZK", "Kč", Placement::AfterSpaced),
+    ("HUF", "Ft", Placement::AfterSpaced),
+    ("SEK", "kr", Placement::AfterSpaced),
+    ("NOK", "kr", Placement::AfterSpaced),
+    ("DKK", "kr", Placement::AfterSpaced),
+    ("BTC", "₿", Placement::Before),
+    ("XBT", "₿", Placement::Before),
+    ("ETH", "Ξ", Placement::Before),
+];
+
+/// Normalizes provider-specific currency codes to their common ISO-style form.
+pub fn normalize_code(code: &str) -> String {
+    match code.to_ascii_uppercase().as_str() {
+        "XBT" => "BTC".to_string(),
+        "XDG" => "DOGE".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Looks up the symbol of a currency. Unknown currencies use their code after the amount.
+pub fn currency_info(code: &str) -> CurrencyInfo {
+    CURRENCIES
+        .iter()
+        .find(|(c, _, _)| c.eq_ignore_ascii_case(code))
+        .map(|&(_, symbol, placement)| CurrencyInfo { symbol, placement })
+        .unwrap_or(CurrencyInfo {
+            symbol: "",
+            placement: Placement::AfterSpaced,
+        })
+}
+
+/// Attaches the currency to an already formatted amount, keeping a leading minus sign
+/// in front of a prefixed symbol ("-$12.00"). A locale placement overrides the
+/// currency's own placement for symbols ("12,00 $" in German).
+pub fn format_amount(
+    amount: &str,
+    code: &str,
+    display: CurrencyDisplay,
+    locale_placement: Option<Placement>,
+) -> String {
+    let (symbol, placement) = match display {
+        CurrencyDisplay::None => return amount.to_string(),
+        CurrencyDisplay::Code => (code, Placement::AfterSpaced),
+        CurrencyDisplay::Symbol => {
+            let info = currency_info(code);
+            if info.symbol.is_empty() {
+                (code, Placement::AfterSpaced)
+            } else {
+                (info.symbol, locale_placement.unwrap_or(info.placement))
+            }
+        }
+    };
+    if symbol.is_empty() {
+        return amount.to_string();
+    }
+
+    let (sign, digits) = match amount.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", amount),
+    };
+    match placement {
+        Placement::Before => format!("{}{}{}", sign, symbol, digits),
+        Placement::BeforeSpaced => format!("{}{} {}", sign, symbol, digits),
+        Placement::AfterSpaced => format!("{} {}", amount, symbol),
+    }
+}
@@ -0,0 +1,140 @@
+use reqwest::blocking::Client;
+use std::collections::HashMap;
+
+use crate::config::{Config, FxConfig, FxProvider};
+use crate::currency;
+use crate::kraken::KrakenProvider;
+use crate::quote::{Conversion, Quote};
+use crate::tiingo::TiingoProvider;
+
+/// Returns how many units of `to` one unit of `from` buys, using the configured provider.
+pub fn rate(
+    from: &str,
+    to: &str,
+    config: &Config,
+    client: &Client,
+) -> Result<f64, Box<dyn std::error::Error>> {
+    if from.eq_ignore_ascii_case(to) {
+        return Ok(1.0);
+    }
+    let fx = &config.fx;
+    match fx.provider {
+        FxProvider::Kraken => {
+            KrakenProvider::new(&config.crypto, client).fx_rate(from, to, fx.cache_max_age)
+        }
+        FxProvider::Tiingo => TiingoProvider::new(&config.stock, &config.exchanges, client)?
+            .fx_rate(from, to, fx.cache_max_age),
+        FxProvider::Static => Ok(static_value(from, fx)? / static_value(to, fx)?),
+    }
+}
+
+/// The value of one unit of `code` in the display currency, according to the static
+/// `[fx] rates`. The display currency itself is worth 1.
+fn static_value(code: &str, fx: &FxConfig) -> Result<f64, Box<dyn std::error::Error>> {
+    let code = currency::normalize_code(code);
+    if fx
+        .display_currency
+        .as_deref()
+        .is_some_and(|display| currency::normalize_code(display) == code)
+    {
+        return Ok(1.0);
+    }
+    fx.rates
+        .iter()
+        .find(|(rate_code, _)| currency::normalize_code(rate_code) == code)
+        .map(|(_, rate)| *rate)
+        .filter(|rate| *rate > 0.0)
+        .ok_or_else(|| format!("No static FX rate for {} in [fx] rates", code).into())
+}
+
+/// Converts a quote into the configured display currency, keeping the original price
+/// for the tooltip. Quotes stay in their own currency if no rate is available.
+pub fn convert_quote(quote: &mut Quote, config: &Config, client: &Client) {
+    convert_quotes([quote], config, client);
+}
+
+/// Converts quotes like `convert_quote`, looking up the rate of each currency only
+/// once, so that a batch of quotes sends at most one request per currency.
+pub fn convert_quotes<'q>(
+    quotes: impl IntoIterator<Item = &'q mut Quote>,
+    config: &Config,
+    client: &Client,
+) {
+    let display_currency = match &config.fx.display_currency {
+        Some(code) => currency::normalize_code(code),
+        None => return,
+    };
+    let mut rates: HashMap<String, Result<f64, String>> = HashMap::new();
+    for quote in quotes {
+        if quote.currency.eq_ignore_ascii_case(&display_currency) {
+            continue;
+        }
+        let rate = rates
+            .entry(quote.currency.to_ascii_uppercase())
+            .or_insert_with(|| {
+                rate(&quote.currency, &display_currency, config, client).map_err(|e| e.to_string())
+            });
+        match rate {
+            Ok(rate) => {
+                let rate = *rate;
+                quote.converted_from = Some(Conversion {
+                    price: quote.price,
+                    currency: quote.currency.clone(),
+                    rate,
+                });
+                quote.price *= rate;
+                quote.reference = quote.reference.map(|reference| reference * rate);
+                quote.high = quote.high.map(|high| high * rate);
+                quote.low = quote.low.map(|low| low * rate);
+                quote.currency = display_currency.clone();
+            }
+            Err(e) => eprintln!(
+                "Could not convert {} from {} to {}: {}",
+                quote.symbol, quote.currency, display_currency, e
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn static_fx(display_currency: Option<&str>) -> FxConfig {
+        FxConfig {
+            display_currency: display_currency.map(str::to_string),
+            provider: FxProvider::Static,
+            rates: HashMap::from([("USD".to_string(), 0.9), ("GBP".to_string(), 1.2)]),
+            ..FxConfig::default()
+        }
+    }
+
+    fn static_rate(from: &str, to: &str, fx: &FxConfig) -> Option<f64> {
+        Some(static_value(from, fx).ok()? / static_value(to, fx).ok()?)
+    }
+
+    #[test]
+    fn static_rates_convert_into_the_display_currency() {
+        let fx = static_fx(Some("EUR"));
+        assert_eq!(static_rate("USD", "EUR", &fx), Some(0.9));
+        assert_eq!(static_rate("eur", "EUR", &fx), Some(1.0));
+    }
+
+    #[test]
+    fn static_rates_convert_out_of_the_display_currency() {
+        let fx = static_fx(Some("EUR"));
+        let rate = static_rate("EUR", "USD", &fx).unwrap();
+        assert!((rate - 1.0 / 0.9).abs() < 1e-12);
+        let rate = static_rate("GBP", "USD", &fx).unwrap();
+        assert!((rate - 1.2 / 0.9).abs() < 1e-12);
+    }
+
+    #[test]
+    fn static_rates_need_both_currencies() {
+        assert_eq!(static_rate("JPY", "EUR", &static_fx(Some("EUR"))), None);
+        // Without a display currency there is no currency worth 1.
+        assert_eq!(static_rate("USD", "EUR", &static_fx(None)), None);
+        let rate = static_rate("GBP", "USD", &static_fx(None)).unwrap();
+        assert!((rate - 1.2 / 0.9).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,688 @@
+use chrono::DateTime;
+use reqwest::blocking::Client;
+use reqwest::StatusCode;
+use serde_json::{json, Map, Value};
+use std::fmt;
+use std::fs;
+use std::sync::{Mutex, OnceLock, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::cache::{atomic_write, cache_timestamp, is_cache_valid, read_through, CachedBody};
+use crate::config::{CryptoConfig, Instrument, ReferenceMode};
+use crate::currency;
+use crate::performance::DailyClose;
+use crate::quote::{Quote, QuoteProvider};
+
+const SECONDS_PER_DAY: u64 = 86_400;
+const DAILY_INTERVAL_MINUTES: u64 = 1440;
+const KRAKEN_API: &str = "https://api.kraken.com/0/public";
+
+/// Rate-limit backoff state shared by all Kraken requests, kept on disk so that
+/// separate single-mode invocations respect it too.
+const BACKOFF_FILE: &str = "cache_kraken_backoff.json";
+/// Serializes updates of the backoff file between the fetch threads.
+static BACKOFF_LOCK: Mutex<()> = Mutex::new(());
+const BACKOFF_BASE_SECS: u64 = 30;
+const BACKOFF_MAX_SECS: u64 = 15 * 60;
+
+/// The list of tradable pairs rarely changes, so it is cached for a day.
+const ASSET_PAIRS_CACHE_FILE: &str = "cache_kraken_assetpairs.json";
+const ASSET_PAIRS_MAX_AGE: u64 = SECONDS_PER_DAY;
+
+/// A configured pair name resolved through the `AssetPairs` endpoint.
+#[derive(Debug, Clone)]
+pub struct ResolvedPair {
+    /// Canonical pair key used in Kraken results, e.g. "XXBTZEUR".
+    pub key: String,
+    /// ISO-style code of the quote currency, e.g. "EUR".
+    pub quote: String,
+}
+
+/// Candidate spellings of a user-supplied pair name as Kraken lists them:
+/// upper case, without a "/" separator, and with Kraken's own asset codes (XBT, XDG).
+fn pair_name_candidates(symbol: &str) -> Vec<String> {
+    let plain = symbol.to_ascii_uppercase().replace('/', "");
+    let mut candidates = vec![plain.clone()];
+    let kraken_codes = plain.replace("BTC", "XBT").replace("DOGE", "XDG");
+    if kraken_codes != plain {
+        candidates.push(kraken_codes);
+    }
+    candidates
+}
+
+/// Looks up `symbol` in the `result` of an `AssetPairs` response, matching the
+/// canonical key, the altname or the websocket name.
+fn find_pair(pairs: &Value, symbol: &str) -> Option<ResolvedPair> {
+    let pairs = pairs.as_object()?;
+    let candidates = pair_name_candidates(symbol);
+    let matches = |name: &str| {
+        let name = name.to_ascii_uppercase().replace('/', "");
+        candidates.contains(&name)
+    };
+
+    pairs.iter().find_map(|(key, info)| {
+        let altname = info.get("altname").and_then(|v| v.as_str()).unwrap_or("");
+        let wsname = info.get("wsname").and_then(|v| v.as_str()).unwrap_or("");
+        if !(matches(key) || matches(altname) || matches(wsname)) {
+            return None;
+        }
+
+        // The websocket name carries the common quote code ("XBT/EUR"); the `quote`
+        // field uses Kraken's prefixed asset codes ("ZEUR").
+        let quote = wsname
+            .split_once('/')
+            .map(|(_, quote)| quote.to_string())
+            .or_else(|| {
+                info.get("quote").and_then(|v| v.as_str()).map(|q| {
+                    if q.len() == 4 && (q.starts_with('X') || q.starts_with('Z')) {
+                        q[1..].to_string()
+                    } else {
+                        q.to_string()
+                    }
+                })
+            })
+            .unwrap_or_default();
+        Some(ResolvedPair {
+            key: key.clone(),
+            quote: currency::normalize_code(&quote),
+        })
+    })
+}
+
+/// Errors reported by the Kraken API, either via HTTP status or the `error` array
+/// of the response envelope.
+#[derive(Debug)]
+pub enum KrakenError {
+    /// Kraken does not know the requested pair.
+    UnknownPair(String),
+    /// Too many requests; no further requests are sent for `retry_in` seconds.
+    RateLimited { retry_in: u64 },
+    /// Kraken is down, busy or in maintenance.
+    Unavailable(String),
+    /// Any other API error or unexpected HTTP status.
+    Api(String),
+}
+
+impl fmt::Display for KrakenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KrakenError::UnknownPair(pair) => write!(
+                f,
+                "Kraken does not know the pair {} (see {}/AssetPairs for valid names)",
+                pair, KRAKEN_API
+            ),
+            KrakenError::RateLimited { retry_in } => write!(
+                f,
+                "Kraken rate limit exceeded, backing off for {} seconds",
+                retry_in
+            ),
+            KrakenError::Unavailable(reason) => write!(f, "Kraken is unavailable: {}", reason),
+            KrakenError::Api(message) => write!(f, "Kraken API error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for KrakenError {}
+
+/// Maps the messages of a Kraken `error` array to an error kind.
+fn classify_api_errors(messages: &[&str], pair: &str) -> KrakenError {
+    let joined = messages.join(", ");
+    if messages
+        .iter()
+        .any(|m| m.starts_with("EQuery:Unknown asset pair"))
+    {
+        KrakenError::UnknownPair(pair.to_string())
+    } else if messages
+        .iter()
+        .any(|m| m.contains("Rate limit") || m.contains("Too many requests"))
+    {
+        KrakenError::RateLimited {
+            retry_in: backoff_remaining().unwrap_or(0),
+        }
+    } else if messages.iter().any(|m| m.starts_with("EService:")) {
+        KrakenError::Unavailable(joined)
+    } else {
+        KrakenError::Api(joined)
+    }
+}
+
+/// Parses a Kraken response envelope and returns its `result`,
+/// or the mapped error if the `error` array is not empty.
+fn parse_envelope(text: &str, pair: &str) -> Result<Value, KrakenError> {
+    let mut envelope: Value = serde_json::from_str(text)
+        .map_err(|err| KrakenError::Api(format!("invalid JSON response: {}", err)))?;
+
+    let messages: Vec<&str> = envelope
+        .get("error")
+        .and_then(|e| e.as_array())
+        .map(|errors| errors.iter().filter_map(|e| e.as_str()).collect())
+        .unwrap_or_default();
+    if !messages.is_empty() {
+        return Err(classify_api_errors(&messages, pair));
+    }
+
+    envelope
+        .get_mut("result")
+        .map(Value::take)
+        .ok_or_else(|| KrakenError::Api("response has no result".to_string()))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Reads the backoff state as `(until, level)`.
+fn read_backoff() -> (u64, u32) {
+    fs::read_to_string(BACKOFF_FILE)
+        .ok()
+        .and_then(|text| serde_json::from_str::<Value>(&text).ok())
+        .map(|state| {
+            (
+                state.get("until").and_then(|v| v.as_u64()).unwrap_or(0),
+                state.get("level").and_then(|v| v.as_u64()).unwrap_or(0) as u32,
+            )
+        })
+        .unwrap_or((0, 0))
+}
+
+/// Seconds left until requests may be sent again, if a backoff is in effect.
+fn backoff_remaining() -> Option<u64> {
+    let (until, _) = read_backoff();
+    let now = unix_now();
+    (until > now).then(|| until - now)
+}
+
+/// Records a rate-limit response and doubles the backoff period.
+/// Returns the number of seconds to wait.
+fn register_rate_limit() -> u64 {
+    let _lock = BACKOFF_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    let (_, level) = read_backoff();
+    let wait = (BACKOFF_BASE_SECS << level.min(10)).min(BACKOFF_MAX_SECS);
+    let state = json!({ "until": unix_now() + wait, "level": level + 1 });
+    if let Err(err) = atomic_write(BACKOFF_FILE, &state.to_string()) {
+        eprintln!("Could not write {}: {}", BACKOFF_FILE, err);
+    }
+    wait
+}
+
+/// Clears the backoff state after a successful request. A backoff that is still in
+/// effect was registered by another thread after this request was sent, so it stays.
+fn reset_backoff() {
+    let _lock = BACKOFF_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    if fs::metadata(BACKOFF_FILE).is_ok() && read_backoff().0 <= unix_now() {
+        let _ = fs::remove_file(BACKOFF_FILE);
+    }
+}
+
+/// The price at the instant `at`, interpolated between the open and close of the
+/// candle covering it. Candles are `[time, open, high, low, close, ...]` arrays whose
+/// `time` starts an `interval`-second period. Returns `None` outside the candles' range.
+fn price_at(candles: &[Value], at: u64, interval: u64) -> Option<f64> {
+    let parse = |v: Option<&Value>| {
+        v.and_then(|v| v.as_str())
+            .and_then(|s| s.parse::<f64>().ok())
+    };
+    candles.iter().find_map(|candle| {
+        let start = candle.get(0)?.as_u64()?;
+        if at < start || at > start + interval {
+            return None;
+        }
+        let open = parse(candle.get(1))?;
+        let close = parse(candle.get(4))?;
+        let fraction = (at - start) as f64 / interval.max(1) as f64;
+        Some(open + (close - open) * fraction)
+    })
+}
+
+/// Crypto quotes from the Kraken public API, combining the OHLC and Ticker endpoints.
+pub struct KrakenProvider<'a> {
+    config: &'a CryptoConfig,
+    client: &'a Client,
+    /// The `result` of the `AssetPairs` response, parsed on first use.
+    asset_pairs: OnceLock<Value>,
+    /// Held while the pair list is loaded, so that threads sharing the provider
+    /// don't request it at the same time.
+    asset_pairs_loading: Mutex<()>,
+}
+
+impl<'a> KrakenProvider<'a> {
+    pub fn new(config: &'a CryptoConfig, client: &'a Client) -> Self {
+        KrakenProvider {
+            config,
+            client,
+            asset_pairs: OnceLock::new(),
+            asset_pairs_loading: Mutex::new(()),
+        }
+    }
+
+    /// Sends a request to the Kraken API and returns the response body. Responses
+    /// carrying a Kraken error are turned into errors, and no request is sent while a
+    /// rate-limit backoff is in effect. `what` names the endpoint in error messages.
+    fn request(
+        &self,
+        url: &str,
+        what: &str,
+        pair: &str,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        if let Some(retry_in) = backoff_remaining() {
+            return Err(KrakenError::RateLimited { retry_in }.into());
+        }
+
+        let response = self
+            .client
+            .get(url)
+            .header("Accept", "application/json")
+            .send()?;
+
+        let status = response.status();
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(KrakenError::RateLimited {
+                retry_in: register_rate_limit(),
+            }
+            .into());
+        }
+        if status.is_server_error() {
+            return Err(KrakenError::Unavailable(format!(
+                "{} request for pair {} returned HTTP status {}",
+                what, pair, status
+            ))
+            .into());
+        }
+        if !status.is_success() {
+            return Err(KrakenError::Api(format!(
+                "Failed to fetch {} data for pair {}: HTTP status {}",
+                what, pair, status
+            ))
+            .into());
+        }
+
+        let text = response.text()?;
+        match parse_envelope(&text, pair) {
+            Ok(_) => {
+                reset_backoff();
+                Ok(text)
+            }
+            Err(KrakenError::RateLimited { .. }) => Err(KrakenError::RateLimited {
+                retry_in: register_rate_limit(),
+            }
+            .into()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Returns the body of `url`, served from `cache_file` while it is younger than
+    /// `cache_max_age` and from the expired cache if the request fails.
+    /// Error responses are never cached.
+    fn fetch_cached(
+        &self,
+        url: &str,
+        cache_file: &str,
+        cache_max_age: u64,
+        what: &str,
+        pair: &str,
+    ) -> Result<CachedBody, Box<dyn std::error::Error>> {
+        read_through(cache_file, cache_max_age, || self.request(url, what, pair))
+    }
+
+    /// Maximum cache age of an instrument's data.
+    fn cache_max_age(&self, instrument: &Instrument) -> u64 {
+        instrument
+            .cache_max_age
+            .unwrap_or(self.config.cache_max_age)
+    }
+}
+
+/// Cache file of a pair's Ticker data.
+fn ticker_cache_file(pair: &str) -> String {
+    format!("cache_crypto_{}_ticker.json", pair)
+}
+
+impl KrakenProvider<'_> {
+    /// The list of tradable pairs, read from the cache or requested once per provider.
+    fn asset_pairs(&self, symbol: &str) -> Result<&Value, Box<dyn std::error::Error>> {
+        if let Some(pairs) = self.asset_pairs.get() {
+            return Ok(pairs);
+        }
+        let _loading = self
+            .asset_pairs_loading
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(pairs) = self.asset_pairs.get() {
+            return Ok(pairs);
+        }
+        let url = format!("{}/AssetPairs", KRAKEN_API);
+        let body = self.fetch_cached(
+            &url,
+            ASSET_PAIRS_CACHE_FILE,
+            ASSET_PAIRS_MAX_AGE,
+            "AssetPairs",
+            symbol,
+        )?;
+        let pairs = parse_envelope(&body.text, symbol)?;
+        Ok(self.asset_pairs.get_or_init(|| pairs))
+    }
+
+    /// Resolves a configured pair name (e.g. "BTCEUR", "XBT/EUR" or "XXBTZEUR") to
+    /// Kraken's canonical key and quote currency.
+    pub fn resolve_pair(&self, symbol: &str) -> Result<ResolvedPair, Box<dyn std::error::Error>> {
+        find_pair(self.asset_pairs(symbol)?, symbol)
+            .ok_or_else(|| KrakenError::UnknownPair(symbol.to_string()).into())
+    }
+
+    /// Returns how many units of `to` one unit of `from` buys, using the last trade of
+    /// the matching Kraken pair (or the inverse pair), cached for `max_age` seconds.
+    pub fn fx_rate(
+        &self,
+        from: &str,
+        to: &str,
+        max_age: u64,
+    ) -> Result<f64, Box<dyn std::error::Error>> {
+        let (resolved, inverse) = match self.resolve_pair(&format!("{}{}", from, to)) {
+            Ok(resolved) => (resolved, false),
+            Err(_) => (self.resolve_pair(&format!("{}{}", to, from))?, true),
+        };
+        let pair = resolved.key.as_str();
+
+        let url = format!("{}/Ticker?pair={}", KRAKEN_API, pair);
+        let cache_file = format!("cache_fx_kraken_{}.json", pair);
+        let body = self.fetch_cached(&url, &cache_file, max_age, "Ticker", pair)?;
+        let last = parse_envelope(&body.text, pair)?
+            .get(pair)
+            .and_then(|data| data.get("c"))
+            .and_then(|c| c.get(0))
+            .and_then(|val| val.as_str())
+            .and_then(|s| s.parse::<f64>().ok())
+            .filter(|price| *price > 0.0)
+            .ok_or_else(|| format!("Could not retrieve FX rate from Kraken pair {}", pair))?;
+
+        Ok(if inverse { 1.0 / last } else { last })
+    }
+}
+
+impl QuoteProvider for KrakenProvider<'_> {
+    /// Fetches crypto data from Kraken for a given trade pair,
+    /// using caching for both OHLC and ticker endpoints.
+    fn fetch(&self, instrument: &Instrument) -> Result<Quote, Box<dyn std::error::Error>> {
+        let resolved = self.resolve_pair(&instrument.symbol)?;
+        let pair = resolved.key.as_str();
+        let cache_max_age = self.cache_max_age(instrument);
+        let chart_interval = instrument
+            .chart_interval
+            .unwrap_or(self.config.chart_interval);
+
+        let cache_file_ticker = ticker_cache_file(pair);
+        let ticker_url = format!("{}/Ticker?pair={}", KRAKEN_API, pair);
+        let ticker = self.fetch_cached(
+            &ticker_url,
+            &cache_file_ticker,
+            cache_max_age,
+            "Ticker",
+            pair,
+        )?;
+
+        let ticker_result = parse_envelope(&ticker.text, pair)?;
+        let ticker_data = ticker_result.get(pair);
+        let current_value = ticker_data
+            .and_then(|pair_data| pair_data.get("p"))
+            .and_then(|p| p.get(0))
+            .and_then(|val| val.as_str())
+            .and_then(|s| s.parse::<f64>().ok())
+            .ok_or_else(|| format!("Could not retrieve current price for crypto pair {}", pair))?;
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let utc_midnight = now - now % SECONDS_PER_DAY;
+        let mut stale = ticker.stale;
+
+        let reference_mode = instrument.reference.unwrap_or(self.config.reference);
+        let (reference, reference_time) = match reference_mode {
+            ReferenceMode::Open => (
+                ticker_data
+                    .and_then(|pair_data| pair_data.get("o"))
+                    .and_then(|val| val.as_str())
+                    .and_then(|s| s.parse::<f64>().ok()),
+                utc_midnight,
+            ),
+            mode => {
+                let target = match mode {
+                    ReferenceMode::UtcMidnight => utc_midnight,
+                    ReferenceMode::HoursAgo(hours) => {
+                        now.saturating_sub(hours.saturating_mul(3600))
+                    }
+                    _ => now - SECONDS_PER_DAY,
+                };
+
+                let cache_file_ohlc = format!("cache_crypto_{}_ohlc.json", pair);
+                let ohlc_url = format!(
+                    "{}/OHLC?pair={}&interval={}",
+                    KRAKEN_API, pair, chart_interval
+                );
+                let ohlc =
+                    self.fetch_cached(&ohlc_url, &cache_file_ohlc, cache_max_age, "OHLC", pair)?;
+                stale |= ohlc.stale;
+
+                let ohlc_result = parse_envelope(&ohlc.text, pair)?;
+                let candles = ohlc_result
+                    .get(pair)
+                    .and_then(|v| v.as_array())
+                    .ok_or_else(|| {
+                        format!("Could not retrieve OHLC candles array for pair {}", pair)
+                    })?;
+                (price_at(candles, target, chart_interval * 60), target)
+            }
+        };
+
+        // Ticker fields hold [today, last 24 hours]; use the rolling 24 hours.
+        let last_24h = |field: &str| {
+            ticker_data
+                .and_then(|pair_data| pair_data.get(field))
+                .and_then(|values| values.get(1))
+                .and_then(|val| val.as_str())
+                .and_then(|s| s.parse::<f64>().ok())
+        };
+
+        Ok(Quote {
+            symbol: instrument.symbol.clone(),
+            price: current_value,
+            reference,
+            high: last_24h("h"),
+            low: last_24h("l"),
+            volume: last_24h("v"),
+            reference_time: Some(reference_time),
+            currency: instrument.currency.clone().unwrap_or(resolved.quote),
+            timestamp: cache_timestamp(&cache_file_ticker)?,
+            source: "Kraken",
+            max_age: cache_max_age,
+            stale,
+            market_reopens: None,
+            converted_from: None,
+            performance: Vec::new(),
+        })
+    }
+
+    /// Fetches daily candles (Kraken returns up to 720 days) and keeps the close of each
+    /// UTC day. Today's candle is still open; its close is the latest trade.
+    fn daily_closes(
+        &self,
+        instrument: &Instrument,
+        max_age: u64,
+    ) -> Result<Vec<DailyClose>, Box<dyn std::error::Error>> {
+        let resolved = self.resolve_pair(&instrument.symbol)?;
+        let pair = resolved.key.as_str();
+        let cache_file = format!("cache_crypto_{}_daily.json", pair);
+        let url = format!(
+            "{}/OHLC?pair={}&interval={}",
+            KRAKEN_API, pair, DAILY_INTERVAL_MINUTES
+        );
+        let body = self.fetch_cached(&url, &cache_file, max_age, "OHLC", pair)?;
+
+        let result = parse_envelope(&body.text, pair)?;
+        let candles = result
+            .get(pair)
+            .and_then(|v| v.as_array())
+            .ok_or_else(|| format!("Could not retrieve daily candles for pair {}", pair))?;
+        Ok(candles
+            .iter()
+            .filter_map(|candle| {
+                let start = candle.get(0)?.as_i64()?;
+                Some(DailyClose {
+                    date: DateTime::from_timestamp(start, 0)?.date_naive(),
+                    close: candle.get(4)?.as_str()?.parse().ok()?,
+                })
+            })
+            .collect())
+    }
+
+    /// Fetches the Ticker data of all pairs whose cache has expired in one
+    /// `Ticker?pair=A,B,C` request and splits it into the per-pair cache files.
+    /// Pairs that can't be resolved are left to `fetch`, which reports them.
+    fn prefetch(&self, instruments: &[&Instrument]) -> Result<(), Box<dyn std::error::Error>> {
+        let mut pairs: Vec<String> = Vec::new();
+        for instrument in instruments {
+            if let Ok(resolved) = self.resolve_pair(&instrument.symbol) {
+                let cache_file = ticker_cache_file(&resolved.key);
+                if !is_cache_valid(&cache_file, self.cache_max_age(instrument))
+                    && !pairs.contains(&resolved.key)
+                {
+                    pairs.push(resolved.key);
+                }
+            }
+        }
+        // A single pair gains nothing from a batch.
+        if pairs.len() < 2 {
+            return Ok(());
+        }
+
+        let joined = pairs.join(",");
+        let url = format!("{}/Ticker?pair={}", KRAKEN_API, joined);
+        let text = self.request(&url, "Ticker", &joined)?;
+        let result = parse_envelope(&text, &joined)?;
+        for pair in &pairs {
+            if let Some(data) = result.get(pair) {
+                let mut pair_result = Map::new();
+                pair_result.insert(pair.clone(), data.clone());
+                let body = json!({ "error": [], "result": pair_result });
+                atomic_write(&ticker_cache_file(pair), &body.to_string())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset_pairs() -> Value {
+        json!({
+            "XXBTZEUR": { "altname": "XBTEUR", "wsname": "XBT/EUR", "quote": "ZEUR" },
+            "XETHXXBT": { "altname": "ETHXBT", "wsname": "ETH/XBT", "quote": "XXBT" },
+            "TBTCEUR": { "altname": "TBTCEUR", "wsname": "TBTC/EUR", "quote": "ZEUR" },
+            "XDGUSD": { "altname": "XDGUSD", "quote": "ZUSD" },
+            "DOTUSDT": { "altname": "DOTUSDT", "quote": "USDT" },
+        })
+    }
+
+    fn resolve(symbol: &str) -> Option<(String, String)> {
+        find_pair(&asset_pairs(), symbol).map(|pair| (pair.key, pair.quote))
+    }
+
+    fn pair(key: &str, quote: &str) -> Option<(String, String)> {
+        Some((key.to_string(), quote.to_string()))
+    }
+
+    #[test]
+    fn common_names_resolve_to_kraken_keys() {
+        assert_eq!(resolve("BTC/EUR"), pair("XXBTZEUR", "EUR"));
+        assert_eq!(resolve("btceur"), pair("XXBTZEUR", "EUR"));
+        assert_eq!(resolve("XBT/EUR"), pair("XXBTZEUR", "EUR"));
+        assert_eq!(resolve("XXBTZEUR"), pair("XXBTZEUR", "EUR"));
+        assert_eq!(resolve("DOGEUSD"), pair("XDGUSD", "USD"));
+        // "TBTCEUR" contains "BTCEUR" but is a different pair.
+        assert_eq!(resolve("TBTCEUR"), pair("TBTCEUR", "EUR"));
+    }
+
+    #[test]
+    fn quote_codes_lose_kraken_prefixes() {
+        assert_eq!(resolve("ETH/BTC"), pair("XETHXXBT", "BTC"));
+        assert_eq!(resolve("DOTUSDT"), pair("DOTUSDT", "USDT"));
+    }
+
+    #[test]
+    fn unknown_pairs_are_not_found() {
+        assert_eq!(resolve("BTCJPY"), None);
+        assert!(find_pair(&json!([]), "BTCEUR").is_none());
+    }
+
+    fn candles() -> Vec<Value> {
+        // [time, open, high, low, close, ...] of two 5-minute candles.
+        vec![
+            json!([1000, "10.0", "12.0", "9.0", "11.0"]),
+            json!([1300, "11.0", "13.0", "10.5", "14.0"]),
+        ]
+    }
+
+    #[test]
+    fn prices_are_interpolated_within_a_candle() {
+        assert_eq!(price_at(&candles(), 1000, 300), Some(10.0));
+        assert_eq!(price_at(&candles(), 1150, 300), Some(10.5));
+        assert_eq!(price_at(&candles(), 1375, 300), Some(11.75));
+        assert_eq!(price_at(&candles(), 1600, 300), Some(14.0));
+    }
+
+    #[test]
+    fn prices_outside_the_candles_are_unknown() {
+        assert_eq!(price_at(&candles(), 999, 300), None);
+        assert_eq!(price_at(&candles(), 1601, 300), None);
+        assert_eq!(
+            price_at(&[json!([1000, "x", "", "", "11.0"])], 1100, 300),
+            None
+        );
+        assert_eq!(price_at(&[], 1000, 300), None);
+    }
+
+    #[test]
+    fn api_errors_are_classified() {
+        let classify = |messages: &[&str]| classify_api_errors(messages, "BTCEUR");
+        assert!(matches!(
+            classify(&["EQuery:Unknown asset pair"]),
+            KrakenError::UnknownPair(pair) if pair == "BTCEUR"
+        ));
+        assert!(matches!(
+            classify(&["EAPI:Rate limit exceeded"]),
+            KrakenError::RateLimited { .. }
+        ));
+        assert!(matches!(
+            classify(&["EGeneral:Too many requests"]),
+            KrakenError::RateLimited { .. }
+        ));
+        assert!(matches!(
+            classify(&["EService:Unavailable", "EService:Busy"]),
+            KrakenError::Unavailable(message) if message == "EService:Unavailable, EService:Busy"
+        ));
+        assert!(matches!(
+            classify(&["EGeneral:Invalid arguments"]),
+            KrakenError::Api(message) if message == "EGeneral:Invalid arguments"
+        ));
+    }
+
+    #[test]
+    fn envelopes_yield_their_result_or_error() {
+        let result = parse_envelope(r#"{"error":[],"result":{"a":1}}"#, "A").unwrap();
+        assert_eq!(result, json!({ "a": 1 }));
+        assert!(matches!(
+            parse_envelope(r#"{"error":["EQuery:Unknown asset pair"]}"#, "A"),
+            Err(KrakenError::UnknownPair(_))
+        ));
+        assert!(matches!(
+            parse_envelope(r#"{"error":[]}"#, "A"),
+            Err(KrakenError::Api(_))
+        ));
+        assert!(matches!(
+            parse_envelope("<html>", "A"),
+            Err(KrakenError::Api(_))
+        ));
+    }
+}
@@ -0,0 +1,30 @@
+use std::sync::{Mutex, PoisonError};
+
+/// The newest value published by a background worker. The reader takes it whenever
+/// it is ready, so it never waits for the worker and skips values it didn't get to.
+pub struct Latest<T> {
+    value: Mutex<Option<T>>,
+}
+
+impl<T> Default for Latest<T> {
+    fn default() -> Self {
+        Latest {
+            value: Mutex::new(None),
+        }
+    }
+}
+
+impl<T> Latest<T> {
+    /// Publishes a value, replacing one that hasn't been taken yet.
+    pub fn publish(&self, value: T) {
+        *self.value.lock().unwrap_or_else(PoisonError::into_inner) = Some(value);
+    }
+
+    /// Takes the value published since the last call, if any.
+    pub fn take(&self) -> Option<T> {
+        self.value
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+    }
+}
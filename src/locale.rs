@@ -0,0 +1,191 @@
+use std::env;
+
+use crate::currency::Placement;
+
+/// Number formatting conventions of a locale.
+#[derive(Debug, Clone, Copy)]
+pub struct Locale {
+    pub decimal_separator: char,
+    pub group_separator: char,
+    /// Where currency symbols go; `None` keeps each currency's customary
+    /// English placement ("$1.00", "1.00 zł").
+    pub currency_placement: Option<Placement>,
+    /// Whether a space separates the number from the percent sign ("-1,23 %").
+    pub percent_spaced: bool,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        ENGLISH
+    }
+}
+
+const ENGLISH: Locale = Locale {
+    decimal_separator: '.',
+    group_separator: ',',
+    currency_placement: None,
+    percent_spaced: false,
+};
+
+/// "1.234,56 €" and "-1,23 %".
+const CONTINENTAL: Locale = Locale {
+    decimal_separator: ',',
+    group_separator: '.',
+    currency_placement: Some(Placement::AfterSpaced),
+    percent_spaced: true,
+};
+
+/// "1 234,56 €" with a narrow no-break space for grouping.
+const SPACE_GROUPED: Locale = Locale {
+    decimal_separator: ',',
+    group_separator: '\u{202F}',
+    currency_placement: Some(Placement::AfterSpaced),
+    percent_spaced: true,
+};
+
+/// Bundled locale rules, matched by full name first and language second.
+const LOCALES: &[(&str, Locale)] = &[
+    ("en", ENGLISH),
+    ("ja", ENGLISH),
+    ("zh", ENGLISH),
+    ("ko", ENGLISH),
+    ("de", CONTINENTAL),
+    ("de_AT", CONTINENTAL),
+    (
+        "de_CH",
+        Locale {
+            decimal_separator: '.',
+            group_separator: '’',
+            currency_placement: Some(Placement::BeforeSpaced),
+            percent_spaced: false,
+        },
+    ),
+    ("es", CONTINENTAL),
+    ("it", CONTINENTAL),
+    ("da", CONTINENTAL),
+    ("tr", CONTINENTAL),
+    (
+        "nl",
+        Locale {
+            decimal_separator: ',',
+            group_separator: '.',
+            currency_placement: Some(Placement::BeforeSpaced),
+            percent_spaced: false,
+        },
+    ),
+    (
+        "pt",
+        Locale {
+            decimal_separator: ',',
+            group_separator: '.',
+            currency_placement: Some(Placement::BeforeSpaced),
+            percent_spaced: false,
+        },
+    ),
+    ("pt_PT", SPACE_GROUPED),
+    ("fr", SPACE_GROUPED),
+    ("fr_CH", SPACE_GROUPED),
+    ("pl", SPACE_GROUPED),
+    ("cs", SPACE_GROUPED),
+    ("sk", SPACE_GROUPED),
+    ("hu", SPACE_GROUPED),
+    ("sv", SPACE_GROUPED),
+    ("nb", SPACE_GROUPED),
+    ("no", SPACE_GROUPED),
+    ("fi", SPACE_GROUPED),
+    ("ru", SPACE_GROUPED),
+    ("uk", SPACE_GROUPED),
+];
+
+/// Looks up a locale name such as "de_DE.UTF-8", "de-DE" or "fr".
+/// "C" and "POSIX" use English rules.
+pub fn lookup(name: &str) -> Option<Locale> {
+    let name = name
+        .split(['.', '@'])
+        .next()
+        .unwrap_or("")
+        .replace('-', "_");
+    if name.is_empty() || name == "C" || name == "POSIX" {
+        return Some(ENGLISH);
+    }
+    let language = name.split('_').next().unwrap_or("");
+    LOCALES
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(&name))
+        .or_else(|| {
+            LOCALES
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(language))
+        })
+        .map(|(_, locale)| *locale)
+}
+
+/// The locale name from the environment, in POSIX precedence order.
+fn from_environment() -> Option<String> {
+    ["LC_ALL", "LC_NUMERIC", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())
+}
+
+/// Resolves the `[format] locale` setting: unset keeps English formatting,
+/// "auto" reads LC_ALL / LC_NUMERIC / LANG, anything else names a locale.
+pub fn resolve(setting: Option<&str>) -> Locale {
+    let name = match setting {
+        None => return ENGLISH,
+        Some(setting) if setting.eq_ignore_ascii_case("auto") => match from_environment() {
+            Some(name) => name,
+            None => return ENGLISH,
+        },
+        Some(setting) => setting.to_string(),
+    };
+    lookup(&name).unwrap_or_else(|| {
+        eprintln!(
+            "Warning: no number formatting rules for locale '{}', using English",
+            name
+        );
+        ENGLISH
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The decimal and group separators of a looked-up locale.
+    fn separators(name: &str) -> Option<(char, char)> {
+        lookup(name).map(|locale| (locale.decimal_separator, locale.group_separator))
+    }
+
+    #[test]
+    fn names_may_carry_region_encoding_and_modifier() {
+        assert_eq!(separators("de_DE.UTF-8"), Some((',', '.')));
+        assert_eq!(separators("de-DE"), Some((',', '.')));
+        assert_eq!(separators("de_DE@euro"), Some((',', '.')));
+        assert_eq!(separators("DE"), Some((',', '.')));
+    }
+
+    #[test]
+    fn regions_take_precedence_over_languages() {
+        assert_eq!(separators("de_CH.UTF-8"), Some(('.', '’')));
+        assert_eq!(separators("pt_BR"), Some((',', '.')));
+        assert_eq!(separators("pt_PT"), Some((',', '\u{202F}')));
+        let swiss = lookup("de_CH").unwrap();
+        assert_eq!(swiss.currency_placement, Some(Placement::BeforeSpaced));
+        assert!(!swiss.percent_spaced);
+    }
+
+    #[test]
+    fn posix_names_use_english_rules() {
+        for name in ["C", "POSIX", "C.UTF-8", ""] {
+            assert_eq!(separators(name), Some(('.', ',')), "{:?}", name);
+        }
+        assert!(lookup("en_US").unwrap().currency_placement.is_none());
+    }
+
+    #[test]
+    fn unknown_locales_are_not_found() {
+        assert!(lookup("xx_YY").is_none());
+        assert!(lookup("zz").is_none());
+    }
+}
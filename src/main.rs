@@ -1,66 +1,34 @@
-use chrono::prelude::*;
+mod cache;
+mod calendar;
+mod config;
+mod currency;
+mod fx;
+mod kraken;
+mod latest;
+mod locale;
+mod number;
+mod output;
+mod overview;
+mod performance;
+mod portfolio;
+mod quote;
+mod template;
+mod ticker;
+mod tiingo;
+
 use reqwest::blocking::Client;
-use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
-use serde::Deserialize;
-use serde_json::{json, Value};
 use std::env;
-use std::fs;
 use std::io::Write;
 use std::thread;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-const SECONDS_PER_DAY: u64 = 86_400;
-
-/// Global thresholds used for both stocks and crypto.
-#[derive(Debug, Deserialize)]
-struct Thresholds {
-    critdown: f64, // if percentage change < critdown then "critdown"
-    down: f64,     // if percentage change < down (but >= critdown) then "down"
-    wayup: f64,    // if percentage change > wayup then "wayup"
-    #[serde(default)]
-    up_color: Option<String>,
-    #[serde(default)]
-    wayup_color: Option<String>,
-    #[serde(default)]
-    down_color: Option<String>,
-    #[serde(default)]
-    waydown_color: Option<String>,
-}
-
-/// Stock (Tiingo) configuration (optional).
-#[derive(Debug, Deserialize)]
-struct StockConfig {
-    tickers: Vec<String>,
-    cache_max_age: u64,         // Cache age for weekdays.
-    weekend_cache_max_age: u64, // Cache age for weekends.
-}
-
-/// Crypto configuration.
-#[derive(Debug, Deserialize)]
-struct CryptoConfig {
-    trade_pairs: Vec<String>,
-    trade_signs: Vec<String>,
-    chart_interval: u64,
-    cache_max_age: u64, // Cache age (in seconds) for crypto data.
-}
-
-/// Top-level configuration.
-#[derive(Debug, Deserialize)]
-struct Config {
-    rotation_seconds: u64, // Global rotation interval for the combined list.
-    stock: Option<StockConfig>,
-    thresholds: Thresholds,
-    crypto: Option<CryptoConfig>,
-    ticker: Option<TickerConfig>,
-}
-
-/// Ticker mode configuration.
-#[derive(Debug, Deserialize)]
-struct TickerConfig {
-    window_size: usize,   // Number of visible characters
-    separator: String,     // Separator between items (e.g., " - ")
-    refresh_seconds: u64,  // How often to refresh data from APIs
-}
+use config::{Config, Instrument, OutputFormat, ProviderKind};
+use kraken::KrakenProvider;
+use latest::Latest;
+use output::Segment;
+use quote::{Quote, QuoteProvider};
+use ticker::Snapshot;
+use tiingo::TiingoProvider;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Load environment variables from .env.local (if it exists)
@@ -73,14 +41,26 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // The "--ticker" flag enables scrolling ticker mode.
     // The "--crypto" flag shows only crypto instruments (ticker mode only).
     // The "--stock" flag shows only stock instruments (ticker mode only).
+    // "--format <name>" selects the output protocol: waybar (default), i3bar, polybar,
+    // lemonbar, xmobar, plain, tmux or ansi.
     let args: Vec<String> = env::args().collect();
     let mut config_file = "config.toml".to_string();
     let mut continuous = false;
     let mut ticker_mode = false;
     let mut filter_mode: Option<&str> = None;
-
-    for arg in args.iter().skip(1) {
-        if arg == "--continuous" {
+    let mut output_format: Option<OutputFormat> = None;
+
+    let mut args_iter = args.iter().skip(1);
+    while let Some(arg) = args_iter.next() {
+        if arg == "--format" || arg.starts_with("--format=") {
+            let name = match arg.strip_prefix("--format=") {
+                Some(name) => name,
+                None => args_iter
+                    .next()
+                    .ok_or("--format requires a value, e.g. waybar or i3bar")?,
+            };
+            output_format = Some(OutputFormat::parse(name)?);
+        } else if arg == "--continuous" {
             continuous = true;
         } else if arg == "--ticker" {
             ticker_mode = true;
@@ -94,38 +74,74 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     // Load configuration.
-    let config_contents = fs::read_to_string(&config_file).map_err(|err| {
-        eprintln!(
-            "Error: Could not read config file '{}': {}",
-            config_file, err
-        );
-        err
-    })?;
-    let config: Config = toml::from_str(&config_contents).map_err(|err| {
-        eprintln!(
-            "Error: Could not parse config file '{}': {}",
-            config_file, err
-        );
-        err
-    })?;
+    let config = Config::load(&config_file)?;
 
     // Create a single HTTP client to reuse across all requests.
     // This enables connection pooling and DNS caching, dramatically reducing DNS queries.
     let client = Client::new();
 
+    // The --format flag takes precedence over [format] output.
+    let output_format = output_format.unwrap_or(config.format.output);
+    if output::needs_stream(output_format) && !ticker_mode && !continuous {
+        return Err("i3bar output requires --continuous or --ticker".into());
+    }
+    if let Some(header) = output::header(output_format) {
+        println!("{}", header);
+    }
+
     if ticker_mode {
-        run_ticker_mode(&config, filter_mode, &client)?;
+        run_ticker_mode(&config, filter_mode, output_format, &client)?;
     } else if continuous {
         loop {
-            output_current_instrument(&config, filter_mode, &client)?;
+            output_current_instrument(&config, filter_mode, output_format, &client)?;
             thread::sleep(Duration::from_secs(config.rotation_seconds));
         }
     } else {
-        output_current_instrument(&config, filter_mode, &client)?;
+        output_current_instrument(&config, filter_mode, output_format, &client)?;
     }
     Ok(())
 }
 
+/// Returns the configured instruments in display order.
+/// The filter_mode parameter can restrict to only "stock" or "crypto" instruments.
+fn collect_instruments<'a>(config: &'a Config, filter_mode: Option<&str>) -> Vec<&'a Instrument> {
+    config
+        .instruments
+        .iter()
+        .filter(|instrument| {
+            filter_mode.is_none() || filter_mode == Some(instrument.provider.category())
+        })
+        .collect()
+}
+
+/// Creates the provider serving instruments of the given kind.
+fn provider_for<'a>(
+    kind: ProviderKind,
+    config: &'a Config,
+    client: &'a Client,
+) -> Result<Box<dyn QuoteProvider + Sync + 'a>, Box<dyn std::error::Error>> {
+    Ok(match kind {
+        ProviderKind::Tiingo => Box::new(TiingoProvider::new(
+            &config.stock,
+            &config.exchanges,
+            client,
+        )?),
+        ProviderKind::Kraken => Box::new(KrakenProvider::new(&config.crypto, client)),
+    })
+}
+
+/// Fetches the current quote for an instrument from its provider and adds the
+/// configured performance periods. The quote is still in its own currency.
+fn fetch_quote(
+    provider: &dyn QuoteProvider,
+    instrument: &Instrument,
+    config: &Config,
+) -> Result<Quote, Box<dyn std::error::Error>> {
+    let mut quote = provider.fetch(instrument)?;
+    performance::attach(&mut quote, provider, instrument, &config.performance);
+    Ok(quote)
+}
+
 /// Combines available stock and crypto instruments, rotates through them,
 /// fetches data for the current instrument, and prints the JSON output on one line.
 /// If neither are defined, the program exits with an error.
@@ -133,353 +149,199 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 fn output_current_instrument(
     config: &Config,
     filter_mode: Option<&str>,
+    output_format: OutputFormat,
     client: &Client,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let mut instruments: Vec<(&str, &str, &str)> = Vec::new();
-
-    // Add stock instruments if defined and if tickers are provided.
-    if filter_mode.is_none() || filter_mode == Some("stock") {
-        if let Some(stock) = &config.stock {
-            if !stock.tickers.is_empty() {
-                for ticker in &stock.tickers {
-                    instruments.push(("stock", ticker, ""));
-                }
-            }
-        }
-    }
-
-    // Add crypto instruments if defined and if trade pairs are provided.
-    if filter_mode.is_none() || filter_mode == Some("crypto") {
-        if let Some(crypto) = &config.crypto {
-            if !crypto.trade_pairs.is_empty() {
-                for (i, pair) in crypto.trade_pairs.iter().enumerate() {
-                    let sign = crypto.trade_signs.get(i).map(|s| s.as_str()).unwrap_or("");
-                    instruments.push(("crypto", pair, sign));
-                }
-            }
-        }
-    }
+    let instruments = collect_instruments(config, filter_mode);
 
     if instruments.is_empty() {
         return Err("No instruments defined in the configuration".into());
     }
 
+    // The portfolio entry, if shown, comes after the instruments.
+    let entries = instruments.len() + portfolio::shows(config, &instruments) as usize;
     let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
-    let index = (now / config.rotation_seconds) % (instruments.len() as u64);
-    let (inst_type, symbol, sign) = instruments[index as usize];
-
-    let output = if inst_type == "stock" {
-        run_tiingo_for_ticker(symbol, config, client)?
-    } else {
-        run_crypto_for_pair(symbol, sign, config, client)?
+    let index = ((now / config.rotation_seconds) % (entries as u64)) as usize;
+
+    // Only Waybar shows tooltips, so the other formats skip fetching the overview. It
+    // is fetched first, so the entry below is served from the batch-filled caches.
+    let overview_quotes = (config.format.overview && output_format == OutputFormat::Waybar)
+        .then(|| fetch_all(&instruments, config, client));
+
+    let mut entry = match instruments.get(index) {
+        Some(instrument) => {
+            // A batch request refreshes the provider's other instruments too, so the
+            // next entries of the rotation are served from the cache.
+            let provider = provider_for(instrument.provider, config, client)?;
+            prefetch(provider.as_ref(), instrument.provider, &instruments);
+            let mut quote = fetch_quote(provider.as_ref(), instrument, config)?;
+            fx::convert_quote(&mut quote, config, client);
+            quote::render(&quote, instrument, config)
+        }
+        None => {
+            let quotes = fetch_holdings(&instruments, config, client);
+            let summary = portfolio::summarize(&quotes, config, client)
+                .ok_or("No portfolio holdings could be fetched")?;
+            portfolio::render(&summary, config)
+        }
     };
 
-    println!("{}", serde_json::to_string(&output)?);
-    Ok(())
-}
-
-/// Checks if a cache file is still valid based on its age.
-fn is_cache_valid(cache_file: &str, max_age_secs: u64) -> bool {
-    fs::metadata(cache_file)
-        .and_then(|m| m.modified())
-        .map(|modified| {
-            SystemTime::now()
-                .duration_since(modified)
-                .unwrap_or(Duration::from_secs(u64::MAX))
-                < Duration::from_secs(max_age_secs)
-        })
-        .unwrap_or(false)
-}
-
-/// Calculates percentage change between two values.
-/// Returns None if the base value is zero to avoid division by zero.
-fn calculate_percentage_change(current: f64, base: f64) -> Option<f64> {
-    if base == 0.0 {
-        None
-    } else {
-        Some(((current - base) / base) * 100.0)
+    if let Some(quotes) = overview_quotes {
+        let summary = summarize_portfolio(&instruments, &quotes, config, client);
+        entry.tooltip = overview::tooltip(&quotes, summary.as_ref(), Some(index), config);
     }
-}
 
-/// Atomically writes content to a file using a temporary file and rename.
-fn atomic_write(file_path: &str, content: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let temp_file = format!("{}.tmp", file_path);
-    fs::write(&temp_file, content)?;
-    fs::rename(&temp_file, file_path)?;
+    println!("{}", output::format_entry(&entry, output_format, config));
     Ok(())
 }
 
-/// Fetches stock data from Tiingo for a given ticker, using caching.
-/// The environment variable `TIINGO_API_KEY` is required.
-fn run_tiingo_for_ticker(
-    ticker: &str,
+/// Fetches the quotes of all instruments with a quantity, skipping those that fail.
+fn fetch_holdings<'a>(
+    instruments: &[&'a Instrument],
     config: &Config,
     client: &Client,
-) -> Result<Value, Box<dyn std::error::Error>> {
-    let stock_config = config.stock.as_ref().ok_or("Stock configuration missing")?;
-
-    let api_key = env::var("TIINGO_API_KEY").map_err(|_| {
-        "TIINGO_API_KEY environment variable not set. Please set it with your Tiingo API key."
-    })?;
-
-    if api_key.trim().is_empty() {
-        return Err("TIINGO_API_KEY environment variable is empty".into());
-    }
-
-    let local_now = Local::now();
-    let effective_cache_max_age =
-        if local_now.weekday() == Weekday::Sat || local_now.weekday() == Weekday::Sun {
-            stock_config.weekend_cache_max_age
-        } else {
-            stock_config.cache_max_age
-        };
-
-    let cache_file = format!("cache_{}.json", ticker);
-    let use_cache = is_cache_valid(&cache_file, effective_cache_max_age);
-
-    let tiingo_url = format!("https://api.tiingo.com/iex/{}", ticker);
-    let response_text = if use_cache {
-        fs::read_to_string(&cache_file)?
-    } else {
-        let response = client
-            .get(&tiingo_url)
-            .header(CONTENT_TYPE, "application/json")
-            .header(AUTHORIZATION, format!("Token {}", api_key))
-            .send()?;
-
-        if !response.status().is_success() {
-            return Err(format!(
-                "Failed to fetch data from Tiingo for ticker {}: HTTP status {}",
-                ticker,
-                response.status()
-            )
-            .into());
-        }
-
-        let text = response.text()?;
-        atomic_write(&cache_file, &text)?;
-        text
-    };
-
-    let cache_age = {
-        let metadata = fs::metadata(&cache_file)?;
-        let modified = metadata.modified()?;
-        SystemTime::now()
-            .duration_since(modified)
-            .unwrap_or(Duration::new(0, 0))
-            .as_secs()
-    };
-
-    let json_data: Value = serde_json::from_str(&response_text)?;
-    let first_entry = json_data.get(0).ok_or_else(|| {
-        format!(
-            "Invalid API response for ticker {}: missing array element",
-            ticker
-        )
-    })?;
-
-    let last_price = first_entry
-        .get("tngoLast")
-        .and_then(|v| v.as_f64())
-        .ok_or_else(|| {
-            format!(
-                "Invalid tngoLast field for ticker {}: {:?}",
-                ticker, first_entry
-            )
-        })?;
-
-    let prev_close = first_entry
-        .get("prevClose")
-        .and_then(|v| v.as_f64())
-        .ok_or_else(|| {
-            format!(
-                "Invalid prevClose field for ticker {}: {:?}",
-                ticker, first_entry
-            )
-        })?;
-
-    let price_change_pct =
-        calculate_percentage_change(last_price, prev_close).ok_or_else(|| {
-            format!(
-                "Previous close is zero for ticker {}, cannot calculate percentage change",
-                ticker
-            )
-        })?;
-
-    let class = if price_change_pct < config.thresholds.down {
-        if price_change_pct < config.thresholds.critdown {
-            "critdown"
-        } else {
-            "down"
-        }
-    } else if price_change_pct > config.thresholds.wayup {
-        "wayup"
-    } else {
-        "up"
-    };
-
-    Ok(json!({
-        "text": format!("{} ${:.2} ({:.2}%)", ticker, last_price, price_change_pct),
-        "tooltip": format!("Cache Age: {} seconds (Max allowed: {} seconds)", cache_age, effective_cache_max_age),
-        "class": class,
-    }))
+) -> Vec<(&'a Instrument, Quote)> {
+    let holdings: Vec<&Instrument> = instruments
+        .iter()
+        .copied()
+        .filter(|instrument| instrument.quantity.is_some())
+        .collect();
+    fetch_all(&holdings, config, client)
+        .into_iter()
+        .filter_map(|(instrument, quote)| Some((instrument, quote?)))
+        .collect()
 }
 
-/// Fetches crypto data from Kraken for a given trade pair,
-/// using caching for both OHLC and ticker endpoints.
-fn run_crypto_for_pair(
-    pair: &str,
-    sign: &str,
+/// Fetches the quotes of all instruments; those that fail are reported and kept as
+/// `None`. Each provider first gets the chance to batch its requests, then its
+/// instruments are fetched on up to `max_concurrent` threads, while the providers
+/// run side by side. The quotes are converted into the display currency once all
+/// threads are done, so FX requests are neither repeated nor sent in parallel.
+fn fetch_all<'a>(
+    instruments: &[&'a Instrument],
     config: &Config,
     client: &Client,
-) -> Result<Value, Box<dyn std::error::Error>> {
-    let crypto = config
-        .crypto
-        .as_ref()
-        .ok_or("Crypto configuration missing")?;
-
-    if crypto.trade_pairs.is_empty() {
-        return Err("No crypto trade pairs configured".into());
-    }
-
-    let cache_max_age = crypto.cache_max_age;
-    let cache_file_ohlc = format!("cache_crypto_{}_ohlc.json", pair);
-    let cache_file_ticker = format!("cache_crypto_{}_ticker.json", pair);
-
-    let use_cache_ohlc = is_cache_valid(&cache_file_ohlc, cache_max_age);
-    let use_cache_ticker = is_cache_valid(&cache_file_ticker, cache_max_age);
-
-    let kraken_api = "https://api.kraken.com/0/public";
-    let ohlc_url = format!(
-        "{}/OHLC?pair={}&interval={}",
-        kraken_api, pair, crypto.chart_interval
-    );
-    let ticker_url = format!("{}/Ticker?pair={}", kraken_api, pair);
-
-    let ohlc_text = if use_cache_ohlc {
-        fs::read_to_string(&cache_file_ohlc)?
-    } else {
-        let response = client
-            .get(&ohlc_url)
-            .header("Accept", "application/json")
-            .send()?;
-
-        if !response.status().is_success() {
-            return Err(format!(
-                "Failed to fetch OHLC data from Kraken for pair {}: HTTP status {}",
-                pair,
-                response.status()
-            )
-            .into());
-        }
-
-        let text = response.text()?;
-        atomic_write(&cache_file_ohlc, &text)?;
-        text
-    };
-
-    let ticker_text = if use_cache_ticker {
-        fs::read_to_string(&cache_file_ticker)?
-    } else {
-        let response = client
-            .get(&ticker_url)
-            .header("Accept", "application/json")
-            .send()?;
-
-        if !response.status().is_success() {
-            return Err(format!(
-                "Failed to fetch Ticker data from Kraken for pair {}: HTTP status {}",
-                pair,
-                response.status()
-            )
-            .into());
-        }
-
-        let text = response.text()?;
-        atomic_write(&cache_file_ticker, &text)?;
-        text
-    };
-
-    let ticker_json: Value = serde_json::from_str(&ticker_text)?;
-    let current_value = ticker_json
-        .get("result")
-        .and_then(|r| r.get(pair))
-        .and_then(|pair_data| pair_data.get("p"))
-        .and_then(|p| p.get(0))
-        .and_then(|val| val.as_str())
-        .and_then(|s| s.parse::<f64>().ok())
-        .ok_or_else(|| format!("Could not retrieve current price for crypto pair {}", pair))?;
-
-    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
-    let yesterday_timestamp = now - SECONDS_PER_DAY;
-
-    let ohlc_json: Value = serde_json::from_str(&ohlc_text)?;
-    let candles = ohlc_json
-        .get("result")
-        .and_then(|r| r.get(pair))
-        .and_then(|v| v.as_array())
-        .ok_or_else(|| format!("Could not retrieve OHLC candles array for pair {}", pair))?;
-
-    let mut old_vwap: Option<f64> = None;
-    for candle in candles {
-        if let Some(ts) = candle.get(0).and_then(|v| v.as_i64()) {
-            if ts <= yesterday_timestamp as i64 {
-                if let Some(close) = candle
-                    .get(4)
-                    .and_then(|v| v.as_str())
-                    .and_then(|s| s.parse::<f64>().ok())
-                {
-                    old_vwap = Some(close);
-                }
+) -> Vec<(&'a Instrument, Option<Quote>)> {
+    let mut quotes: Vec<Option<Quote>> = vec![None; instruments.len()];
+    thread::scope(|scope| {
+        let mut providers = Vec::new();
+        for (kind, max_concurrent) in [
+            (ProviderKind::Tiingo, config.stock.max_concurrent),
+            (ProviderKind::Kraken, config.crypto.max_concurrent),
+        ] {
+            let indices: Vec<usize> = (0..instruments.len())
+                .filter(|&i| instruments[i].provider == kind)
+                .collect();
+            if indices.is_empty() {
+                continue;
             }
+            providers.push(scope.spawn(move || {
+                let provider = match provider_for(kind, config, client) {
+                    Ok(provider) => provider,
+                    Err(e) => {
+                        for &i in &indices {
+                            eprintln!("Error fetching {}: {}", instruments[i].symbol, e);
+                        }
+                        return Vec::new();
+                    }
+                };
+                // The threads share the provider, which holds what the batch resolved.
+                let provider = provider.as_ref();
+                prefetch(provider, kind, instruments);
+
+                thread::scope(|scope| {
+                    // Every thread takes every `max_concurrent`-th instrument.
+                    let lanes: Vec<_> = (0..max_concurrent.min(indices.len()))
+                        .map(|lane| {
+                            let lane: Vec<usize> = indices
+                                .iter()
+                                .copied()
+                                .skip(lane)
+                                .step_by(max_concurrent)
+                                .collect();
+                            scope.spawn(move || {
+                                lane.into_iter()
+                                    .map(|i| (i, fetch_logged(provider, instruments[i], config)))
+                                    .collect::<Vec<_>>()
+                            })
+                        })
+                        .collect();
+                    lanes
+                        .into_iter()
+                        .flat_map(|lane| lane.join().expect("fetch thread panicked"))
+                        .collect::<Vec<_>>()
+                })
+            }));
         }
-    }
-
-    let old_vwap = old_vwap.unwrap_or(current_value);
-    let change_percentage_opt = calculate_percentage_change(current_value, old_vwap);
-
-    let change_percentage_str = match change_percentage_opt {
-        Some(val) => format!("{:.2}", val),
-        None => "NA".to_string(),
-    };
-
-    let status_class = if let Some(change) = change_percentage_opt {
-        if change < config.thresholds.down {
-            if change < config.thresholds.critdown {
-                "critdown"
-            } else {
-                "down"
+        for provider in providers {
+            for (i, quote) in provider.join().expect("fetch thread panicked") {
+                quotes[i] = quote;
             }
-        } else if change > config.thresholds.wayup {
-            "wayup"
-        } else {
-            "up"
         }
-    } else {
-        "up"
-    };
+    });
+    fx::convert_quotes(quotes.iter_mut().flatten(), config, client);
+    instruments.iter().copied().zip(quotes).collect()
+}
 
-    let current_value_str = format!("{:.2}", current_value);
+/// Lets the provider fetch the data of all its instruments (those of `kind`) in one
+/// request, where its API allows it. A failure is only reported, as the instruments
+/// are then fetched one by one.
+fn prefetch(provider: &dyn QuoteProvider, kind: ProviderKind, instruments: &[&Instrument]) {
+    let batch: Vec<&Instrument> = instruments
+        .iter()
+        .copied()
+        .filter(|instrument| instrument.provider == kind)
+        .collect();
+    if let Err(e) = provider.prefetch(&batch) {
+        eprintln!(
+            "Error fetching {} quotes in one request: {}",
+            kind.category(),
+            e
+        );
+    }
+}
 
-    // Format text: use sign if provided, otherwise use pair name
-    let display_name = if sign.is_empty() {
-        pair.to_string()
-    } else {
-        sign.to_string()
-    };
+/// Fetches a quote, reporting a failure instead of returning it.
+fn fetch_logged(
+    provider: &dyn QuoteProvider,
+    instrument: &Instrument,
+    config: &Config,
+) -> Option<Quote> {
+    match fetch_quote(provider, instrument, config) {
+        Ok(quote) => Some(quote),
+        Err(e) => {
+            eprintln!("Error fetching {}: {}", instrument.symbol, e);
+            None
+        }
+    }
+}
 
-    Ok(json!({
-        "text": format!("{} €{} ({}%)", display_name, current_value_str, change_percentage_str),
-        "tooltip": format!("€{} ({}%)", current_value_str, change_percentage_str),
-        "class": status_class,
-    }))
+/// Sums up the fetched holdings, if the portfolio entry is shown.
+fn summarize_portfolio(
+    instruments: &[&Instrument],
+    quotes: &[(&Instrument, Option<Quote>)],
+    config: &Config,
+    client: &Client,
+) -> Option<portfolio::Summary> {
+    if !portfolio::shows(config, instruments) {
+        return None;
+    }
+    let holdings: Vec<(&Instrument, Quote)> = quotes
+        .iter()
+        .filter(|(instrument, _)| instrument.quantity.is_some())
+        .filter_map(|(instrument, quote)| Some((*instrument, quote.clone()?)))
+        .collect();
+    portfolio::summarize(&holdings, config, client)
 }
 
-/// Runs ticker mode as a persistent loop: scrolls the ticker every second
-/// and re-fetches data every `refresh_seconds`.
+/// Runs ticker mode as a persistent loop: scrolls the ticker every `scroll_interval_ms`
+/// while a background worker re-fetches data every `refresh_seconds` and publishes
+/// it as a new snapshot. The render loop only picks up the latest snapshot, so slow
+/// API requests never stall the scrolling.
 fn run_ticker_mode(
     config: &Config,
     filter_mode: Option<&str>,
+    output_format: OutputFormat,
     client: &Client,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let ticker_config = config
@@ -488,309 +350,145 @@ fn run_ticker_mode(
         .ok_or("Ticker configuration missing. Add [ticker] section to config.toml")?;
 
     let refresh_interval = Duration::from_secs(ticker_config.refresh_seconds);
-    let mut position: usize = 0;
-    let mut ticker_string = build_ticker_string(config, filter_mode, &ticker_config.separator, client)?;
-    let mut ticker_length = get_plain_text_length(&ticker_string);
-    let mut last_refresh = Instant::now();
+    let scroll_interval = Duration::from_millis(ticker_config.scroll_interval_ms);
+    let pause = Duration::from_millis(ticker_config.pause_ms);
+    // The first fetch happens up front, as there is nothing to show before it.
+    // Instruments that fail are reported and left out; only a ticker without any
+    // data ends the program.
+    let snapshot = build_ticker(config, filter_mode, &ticker_config.separator, client)?;
+    let mut tooltip = snapshot.tooltip;
+    let window_size = ticker_config.window_size;
+    let lay_out = |segments| {
+        ticker::layout(
+            segments,
+            &ticker_config.separator,
+            window_size,
+            !pause.is_zero(),
+        )
+    };
+    let mut layout = lay_out(snapshot.segments);
 
-    if ticker_length == 0 {
+    if layout.length == 0 {
         return Err("Ticker string is empty".into());
     }
 
+    let latest: Latest<Snapshot> = Latest::default();
     let stdout = std::io::stdout();
 
-    loop {
-        // Re-fetch data if refresh interval has elapsed
-        if last_refresh.elapsed() >= refresh_interval {
-            match build_ticker_string(config, filter_mode, &ticker_config.separator, client) {
-                Ok(new_string) => {
-                    let new_length = get_plain_text_length(&new_string);
-                    if new_length > 0 {
-                        ticker_string = new_string;
-                        ticker_length = new_length;
-                        if position >= ticker_length {
-                            position = 0;
-                        }
-                    }
-                }
+    thread::scope(|scope| {
+        scope.spawn(|| loop {
+            thread::sleep(refresh_interval);
+            match build_ticker(config, filter_mode, &ticker_config.separator, client) {
+                Ok(snapshot) => latest.publish(snapshot),
                 Err(e) => eprintln!("Error refreshing ticker data: {}", e),
             }
-            last_refresh = Instant::now();
-        }
-
-        let window = get_ticker_window(&ticker_string, position, ticker_config.window_size);
-        let output = json!({
-            "text": window,
-            "tooltip": "Stock Ticker",
-            "class": "ticker",
         });
-        {
-            let mut out = stdout.lock();
-            let _ = writeln!(out, "{}", output);
-            let _ = out.flush();
-        }
-
-        position = (position + 1) % ticker_length;
-        thread::sleep(Duration::from_secs(1));
-    }
-}
-
-/// Builds the complete ticker string with all instruments and formatting.
-fn build_ticker_string(
-    config: &Config,
-    filter_mode: Option<&str>,
-    separator: &str,
-    client: &Client,
-) -> Result<String, Box<dyn std::error::Error>> {
-    let mut items = Vec::new();
 
-    // Collect stock data
-    if filter_mode.is_none() || filter_mode == Some("stock") {
-        if let Some(stock) = &config.stock {
-            for ticker in &stock.tickers {
-                match run_tiingo_for_ticker(ticker, config, client) {
-                    Ok(data) => {
-                        let text = data["text"].as_str().unwrap_or("");
-                        let class = data["class"].as_str().unwrap_or("up");
-                        let color = get_color_for_class(class, config);
-                        let escaped_text = escape_markup(text);
-                        items.push(format!(
-                            "<span color='{}'><b>{}</b></span>",
-                            color, escaped_text
-                        ));
+        let mut position: usize = 0;
+        let mut next_tick = Instant::now();
+        loop {
+            if let Some(snapshot) = latest.take() {
+                let new_layout = lay_out(snapshot.segments);
+                if new_layout.length > 0 {
+                    layout = new_layout;
+                    tooltip = snapshot.tooltip;
+                    if position >= layout.length {
+                        position = 0;
                     }
-                    Err(e) => eprintln!("Error fetching {}: {}", ticker, e),
                 }
             }
-        }
-    }
 
-    // Collect crypto data
-    if filter_mode.is_none() || filter_mode == Some("crypto") {
-        if let Some(crypto) = &config.crypto {
-            for (i, pair) in crypto.trade_pairs.iter().enumerate() {
-                let sign = crypto.trade_signs.get(i).map(|s| s.as_str()).unwrap_or("");
-                match run_crypto_for_pair(pair, sign, config, client) {
-                    Ok(data) => {
-                        let text = data["text"].as_str().unwrap_or("");
-                        let class = data["class"].as_str().unwrap_or("up");
-                        let color = get_color_for_class(class, config);
-                        let escaped_text = escape_markup(text);
-                        items.push(format!(
-                            "<span color='{}'><b>{}</b></span>",
-                            color, escaped_text
-                        ));
-                    }
-                    Err(e) => eprintln!("Error fetching {}: {}", pair, e),
-                }
+            // Content that fits is shown as is; scrolling resumes once it grows.
+            let window = if layout.fits {
+                position = 0;
+                ticker::align(&layout.segments, window_size, ticker_config.static_align)
+            } else {
+                ticker::window(&layout.segments, position, window_size)
+            };
+            let output = output::format_ticker(&window, &tooltip, output_format);
+            {
+                let mut out = stdout.lock();
+                let _ = writeln!(out, "{}", output);
+                let _ = out.flush();
             }
-        }
-    }
-
-    if items.is_empty() {
-        return Err("No data available for ticker".into());
-    }
-
-    Ok(items.join(separator))
-}
-
-/// Gets the color for a given class from config.
-fn get_color_for_class(class: &str, config: &Config) -> String {
-    match class {
-        "critdown" => config
-            .thresholds
-            .waydown_color
-            .clone()
-            .unwrap_or_else(|| "#800000".to_string()),
-        "down" => config
-            .thresholds
-            .down_color
-            .clone()
-            .unwrap_or_else(|| "#FF0000".to_string()),
-        "wayup" => config
-            .thresholds
-            .wayup_color
-            .clone()
-            .unwrap_or_else(|| "#008000".to_string()),
-        _ => config
-            .thresholds
-            .up_color
-            .clone()
-            .unwrap_or_else(|| "#00FF00".to_string()),
-    }
-}
-
-/// Escapes markup-sensitive characters so ticker text cannot inject markup.
-fn escape_markup(s: &str) -> String {
-    let mut escaped = String::with_capacity(s.len());
-    for ch in s.chars() {
-        match ch {
-            '&' => escaped.push_str("&amp;"),
-            '<' => escaped.push_str("&lt;"),
-            '>' => escaped.push_str("&gt;"),
-            '\'' => escaped.push_str("&apos;"),
-            '"' => escaped.push_str("&quot;"),
-            _ => escaped.push(ch),
-        }
-    }
-    escaped
-}
-
-/// Extracts a window from the ticker string, handling wrapping and preserving complete markup.
-fn get_ticker_window(full_string: &str, position: usize, window_size: usize) -> String {
-    let plain_text = strip_markup(full_string);
-    let plain_chars: Vec<char> = plain_text.chars().collect();
-    let plain_len = plain_chars.len();
-
-    if plain_len == 0 {
-        return String::new();
-    }
-
-    // Build a mapping of plain text positions to their formatting
-    let char_formats = collect_char_formats(full_string);
 
-    let mut result = String::new();
-    let mut last_color: Option<String> = None;
-
-    for i in 0..window_size {
-        let pos = (position + i) % plain_len;
-
-        if let Some(format_info) = char_formats.get(&pos) {
-            // Check if color changed
-            if last_color != format_info.color {
-                // Close previous format if any
-                if last_color.is_some() {
-                    result.push_str("</b></span>");
-                }
-                // Open new format
-                if let Some(color) = &format_info.color {
-                    result.push_str(&format!("<span color='{}'>", color));
-                    result.push_str("<b>");
-                }
-                last_color = format_info.color.clone();
+            let mut paused = false;
+            if !layout.fits {
+                (position, paused) = ticker::advance(
+                    position,
+                    layout.length,
+                    ticker_config.step,
+                    ticker_config.direction,
+                    &layout.stops,
+                );
             }
-
-            result.push(format_info.character);
-        } else if let Some(ch) = plain_chars.get(pos) {
-            // No format for this character, close any open tags
-            if last_color.is_some() {
-                result.push_str("</b></span>");
-                last_color = None;
+            // After a stall, e.g. a suspend, carry on from now instead of catching up.
+            let now = Instant::now();
+            if now > next_tick + scroll_interval {
+                next_tick = now;
             }
-            result.push(*ch);
+            next_tick += if paused {
+                scroll_interval + pause
+            } else {
+                scroll_interval
+            };
+            thread::sleep(next_tick.saturating_duration_since(Instant::now()));
         }
-    }
-
-    // Close any remaining open tags
-    if last_color.is_some() {
-        result.push_str("</b></span>");
-    }
-
-    result
-}
-
-#[derive(Debug, PartialEq, Eq, Clone)]
-struct FormatInfo {
-    character: char,
-    color: Option<String>,
+    })
 }
 
-/// Collects character format information from the markup string.
-fn collect_char_formats(s: &str) -> std::collections::HashMap<usize, FormatInfo> {
-    use std::collections::HashMap;
-
-    let mut result: HashMap<usize, FormatInfo> = HashMap::new();
-    let mut position = 0;
-    let mut color_stack: Vec<String> = Vec::new();
-    let mut current_color: Option<String> = None;
-    let mut in_tag = false;
-    let mut current_tag = String::new();
-
-    for ch in s.chars() {
-        if ch == '<' {
-            in_tag = true;
-            current_tag.clear();
-            current_tag.push(ch);
-        } else if ch == '>' {
-            current_tag.push(ch);
-            in_tag = false;
-
-            let tag_lower = current_tag.to_ascii_lowercase();
-            if tag_lower.starts_with("<span") {
-                match extract_color_value_from_tag(&current_tag) {
-                    Some(color) => {
-                        color_stack.push(color.clone());
-                        current_color = Some(color);
-                    }
-                    None => eprintln!("Malformed <span> tag encountered: {}", current_tag),
-                }
-            } else if tag_lower == "</span>" {
-                if color_stack.pop().is_none() {
-                    eprintln!("Unmatched </span> tag encountered in ticker text: {}", s);
-                }
-                current_color = color_stack.last().cloned();
-            } else if tag_lower == "</b>" {
-                // Ignored – bold tags do not affect color tracking.
-            }
-        } else if in_tag {
-            current_tag.push(ch);
-        } else {
-            result.insert(
-                position,
-                FormatInfo {
-                    character: ch,
-                    color: current_color.clone(),
-                },
-            );
-            position += 1;
+/// Builds the complete ticker: every instrument in its class color, joined by the
+/// separator, with the overview table as tooltip if enabled.
+fn build_ticker(
+    config: &Config,
+    filter_mode: Option<&str>,
+    separator: &str,
+    client: &Client,
+) -> Result<Snapshot, Box<dyn std::error::Error>> {
+    let mut items = Vec::new();
+    let instruments = collect_instruments(config, filter_mode);
+    let quotes = fetch_all(&instruments, config, client);
+
+    for (instrument, quote) in &quotes {
+        if let Some(quote) = quote {
+            let text = quote::format_ticker_text(quote, instrument, config);
+            let class = quote::classify(quote.change_pct(), &config.thresholds);
+            items.push(Segment {
+                text,
+                color: Some(output::color_for_class(class, config)),
+            });
         }
     }
 
-    result
-}
-
-fn extract_color_value_from_tag(tag: &str) -> Option<String> {
-    const COLOR_ATTR: &str = "color=";
-
-    let attr_pos = tag.find(COLOR_ATTR)?;
-    let mut rest = &tag[attr_pos + COLOR_ATTR.len()..];
-    rest = rest.trim_start();
-
-    let mut chars = rest.chars();
-    let quote = chars.next()?;
-    if quote != '\'' && quote != '"' {
-        eprintln!("Color attribute missing quotes in tag: {}", tag);
-        return None;
+    let summary = summarize_portfolio(&instruments, &quotes, config, client);
+    if let Some(summary) = &summary {
+        let text = portfolio::format_text(summary, config);
+        let class = quote::classify(summary.change_pct(), &config.thresholds);
+        items.push(Segment {
+            text,
+            color: Some(output::color_for_class(class, config)),
+        });
     }
 
-    let remainder = chars.as_str();
-    if let Some(end_idx) = remainder.find(quote) {
-        Some(remainder[..end_idx].to_string())
-    } else {
-        eprintln!("Color attribute missing closing quote in tag: {}", tag);
-        None
+    if items.is_empty() {
+        return Err("No data available for ticker".into());
     }
-}
 
-/// Strips markup tags from a string to get plain text length.
-fn strip_markup(s: &str) -> String {
-    let mut result = String::new();
-    let mut in_tag = false;
-
-    for ch in s.chars() {
-        if ch == '<' {
-            in_tag = true;
-        } else if ch == '>' {
-            in_tag = false;
-        } else if !in_tag {
-            result.push(ch);
+    let mut segments = Vec::with_capacity(items.len() * 2);
+    for item in items {
+        if !segments.is_empty() {
+            segments.push(Segment {
+                text: separator.to_string(),
+                color: None,
+            });
         }
+        segments.push(item);
     }
 
-    result
-}
-
-/// Gets the plain text length (excluding markup).
-fn get_plain_text_length(s: &str) -> usize {
-    strip_markup(s).chars().count()
+    let tooltip = if config.format.overview {
+        overview::tooltip(&quotes, summary.as_ref(), None, config)
+    } else {
+        "Stock Ticker".to_string()
+    };
+    Ok(Snapshot { segments, tooltip })
 }
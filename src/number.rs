@@ -0,0 +1,135 @@
+use crate::config::Decimals;
+
+/// Suffixes used by compact formatting, largest first.
+const COMPACT_SUFFIXES: &[(f64, &str)] = &[(1e12, "T"), (1e9, "B"), (1e6, "M"), (1e3, "k")];
+
+/// Separators used when rendering numbers.
+#[derive(Debug, Clone, Copy)]
+pub struct NumberStyle {
+    pub decimal_separator: char,
+    /// Inserted between groups of three integer digits, if set.
+    pub group_separator: Option<char>,
+}
+
+/// Resolves the number of decimals for `value`. In auto mode, prices of 1 and above
+/// keep at least two decimals and smaller prices get enough decimals to show
+/// `significant_figures` significant digits.
+pub fn resolve_decimals(value: f64, decimals: Decimals, significant_figures: usize) -> usize {
+    match decimals {
+        Decimals::Fixed(n) => n,
+        Decimals::Auto => {
+            if value == 0.0 || !value.is_finite() {
+                return 2;
+            }
+            let magnitude = value.abs().log10().floor() as i64;
+            let needed = significant_figures as i64 - 1 - magnitude;
+            needed.clamp(2, 12) as usize
+        }
+    }
+}
+
+/// Formats `value` with a fixed number of decimals and the given separators.
+pub fn format_number(value: f64, decimals: usize, style: NumberStyle) -> String {
+    let formatted = format!("{:.*}", decimals, value.abs());
+    let (integer, fraction) = match formatted.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (formatted.as_str(), None),
+    };
+
+    let mut result = String::with_capacity(formatted.len() + integer.len() / 3 + 1);
+    // "-0.00" is shown as "0.00".
+    if value < 0.0 && formatted.chars().any(|c| c.is_ascii_digit() && c != '0') {
+        result.push('-');
+    }
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            if let Some(separator) = style.group_separator {
+                result.push(separator);
+            }
+        }
+        result.push(digit);
+    }
+    if let Some(fraction) = fraction {
+        result.push(style.decimal_separator);
+        result.push_str(fraction);
+    }
+    result
+}
+
+/// Formats a price: compact values of 1000 and above get a suffix and one decimal
+/// ("712.3k"); all others use the resolved number of decimals.
+pub fn format_price(
+    value: f64,
+    decimals: Decimals,
+    significant_figures: usize,
+    compact: bool,
+    style: NumberStyle,
+) -> String {
+    if compact && value.abs() >= 1e3 {
+        // A suffix is used once the next smaller one would round to 1000, so that
+        // 999,950 becomes "1.0M" rather than "1,000.0k".
+        if let Some(&(scale, suffix)) = COMPACT_SUFFIXES
+            .iter()
+            .find(|(scale, _)| (value.abs() / (scale / 1e3) * 10.0).round() >= 1e4)
+        {
+            return format!("{}{}", format_number(value / scale, 1, style), suffix);
+        }
+    }
+    let decimals = resolve_decimals(value, decimals, significant_figures);
+    format_number(value, decimals, style)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAIN: NumberStyle = NumberStyle {
+        decimal_separator: '.',
+        group_separator: Some(','),
+    };
+
+    fn compact(value: f64) -> String {
+        format_price(value, Decimals::Auto, 4, true, PLAIN)
+    }
+
+    #[test]
+    fn auto_decimals_keep_significant_figures() {
+        assert_eq!(resolve_decimals(1234.5, Decimals::Auto, 4), 2);
+        assert_eq!(resolve_decimals(1.5, Decimals::Auto, 4), 3);
+        assert_eq!(resolve_decimals(0.012345, Decimals::Auto, 4), 5);
+        assert_eq!(resolve_decimals(1e-20, Decimals::Auto, 4), 12);
+        assert_eq!(resolve_decimals(0.0, Decimals::Auto, 4), 2);
+        assert_eq!(resolve_decimals(f64::NAN, Decimals::Auto, 4), 2);
+        assert_eq!(resolve_decimals(0.0001, Decimals::Fixed(3), 4), 3);
+    }
+
+    #[test]
+    fn numbers_are_grouped_and_signed() {
+        assert_eq!(format_number(1_234_567.891, 2, PLAIN), "1,234,567.89");
+        assert_eq!(format_number(-1234.0, 0, PLAIN), "-1,234");
+        assert_eq!(format_number(-0.001, 2, PLAIN), "0.00");
+        let german = NumberStyle {
+            decimal_separator: ',',
+            group_separator: Some('.'),
+        };
+        assert_eq!(format_number(1234.5, 2, german), "1.234,50");
+    }
+
+    #[test]
+    fn compact_prices_get_a_suffix_from_1000() {
+        assert_eq!(compact(999.95), "999.95");
+        assert_eq!(compact(1000.0), "1.0k");
+        assert_eq!(compact(712_345.0), "712.3k");
+        assert_eq!(compact(-2_500_000.0), "-2.5M");
+        assert_eq!(compact(1.5e15), "1,500.0T");
+    }
+
+    #[test]
+    fn compact_prices_move_up_when_they_round_to_1000() {
+        assert_eq!(compact(999_949.0), "999.9k");
+        assert_eq!(compact(999_950.0), "1.0M");
+        assert_eq!(compact(950_000.0), "950.0k");
+        assert_eq!(compact(999_950_000.0), "1.0B");
+        assert_eq!(compact(-999_950.0), "-1.0M");
+    }
+}
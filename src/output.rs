@@ -0,0 +1,233 @@
+use serde_json::json;
+use std::env;
+use std::sync::OnceLock;
+
+use crate::config::{Config, OutputFormat, WaybarAlt, WaybarConfig};
+use crate::quote::Rendered;
+
+/// A run of text in one color, or in the bar's default color.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub text: String,
+    pub color: Option<String>,
+}
+
+/// Gets the color for a given class from config.
+pub fn color_for_class(class: &str, config: &Config) -> String {
+    match class {
+        "critdown" => config
+            .thresholds
+            .waydown_color
+            .clone()
+            .unwrap_or_else(|| "#800000".to_string()),
+        "down" => config
+            .thresholds
+            .down_color
+            .clone()
+            .unwrap_or_else(|| "#FF0000".to_string()),
+        "wayup" => config
+            .thresholds
+            .wayup_color
+            .clone()
+            .unwrap_or_else(|| "#008000".to_string()),
+        _ => config
+            .thresholds
+            .up_color
+            .clone()
+            .unwrap_or_else(|| "#00FF00".to_string()),
+    }
+}
+
+/// Escapes markup-sensitive characters so text cannot inject Pango markup.
+pub fn escape_pango(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '\'' => escaped.push_str("&apos;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// Parses a "#rrggbb" color.
+fn hex_to_rgb(color: &str) -> Option<(u8, u8, u8)> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// Whether the terminal announces 24-bit color support through `COLORTERM`.
+fn supports_truecolor() -> bool {
+    static TRUECOLOR: OnceLock<bool> = OnceLock::new();
+    *TRUECOLOR.get_or_init(|| {
+        env::var("COLORTERM")
+            .map(|value| value == "truecolor" || value == "24bit")
+            .unwrap_or(false)
+    })
+}
+
+/// The closest color of the xterm 256-color palette: the 6x6x6 cube, or the
+/// grayscale ramp for grays.
+fn rgb_to_ansi256(r: u8, g: u8, b: u8) -> u8 {
+    if r == g && g == b {
+        return match r {
+            0..=7 => 16,
+            249..=255 => 231,
+            _ => 232 + ((r as u16 - 8) * 24 / 241) as u8,
+        };
+    }
+    let level = |c: u8| ((c as u16 * 5 + 127) / 255) as u8;
+    16 + 36 * level(r) + 6 * level(g) + level(b)
+}
+
+/// Bold ANSI escape sequence for a color: truecolor if the terminal supports it,
+/// the nearest of the 256 palette colors otherwise.
+fn ansi_color(r: u8, g: u8, b: u8) -> String {
+    if supports_truecolor() {
+        format!("\x1b[1;38;2;{};{};{}m", r, g, b)
+    } else {
+        format!("\x1b[1;38;5;{}m", rgb_to_ansi256(r, g, b))
+    }
+}
+
+/// Escapes text for the markup of `format`, so it can't open a tag of its own.
+fn escape_text(text: &str, format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Waybar | OutputFormat::I3bar => escape_pango(text),
+        // Polybar reads "%%{" as a literal "%{"; lemonbar swallows every single "%".
+        OutputFormat::Polybar => text.replace("%{", "%%{"),
+        OutputFormat::Lemonbar => text.replace('%', "%%"),
+        // Xmobar shows the text of a `<raw=len:text/>` tag as it is.
+        OutputFormat::Xmobar => text.replace('<', "<raw=1:</>"),
+        // "#" starts a format sequence in the status line and is doubled.
+        OutputFormat::Tmux => text.replace('#', "##"),
+        _ => text.to_string(),
+    }
+}
+
+/// Renders one segment in the color markup of the output format.
+fn render_segment(segment: &Segment, format: OutputFormat) -> String {
+    let text = escape_text(&segment.text, format);
+    match (format, &segment.color) {
+        (OutputFormat::Waybar | OutputFormat::I3bar, Some(color)) => {
+            format!("<span color='{}'><b>{}</b></span>", color, text)
+        }
+        (OutputFormat::Polybar | OutputFormat::Lemonbar, Some(color)) => {
+            format!("%{{F{}}}{}%{{F-}}", color, text)
+        }
+        (OutputFormat::Xmobar, Some(color)) => format!("<fc={}>{}</fc>", color, text),
+        (OutputFormat::Tmux, Some(color)) => format!("#[fg={},bold]{}#[default]", color, text),
+        (OutputFormat::Ansi, Some(color)) => match hex_to_rgb(color) {
+            Some((r, g, b)) => format!("{}{}\x1b[0m", ansi_color(r, g, b), text),
+            None => text,
+        },
+        _ => text,
+    }
+}
+
+/// Renders colored segments as one line of markup.
+pub fn render_segments(segments: &[Segment], format: OutputFormat) -> String {
+    segments
+        .iter()
+        .map(|segment| render_segment(segment, format))
+        .collect()
+}
+
+/// Lines printed once before any output, for protocols that need a header.
+pub fn header(format: OutputFormat) -> Option<String> {
+    match format {
+        // Protocol header, then an infinite array with one status line per update.
+        OutputFormat::I3bar => Some(format!("{}\n[", json!({ "version": 1 }))),
+        _ => None,
+    }
+}
+
+/// Whether the format only works in a long-running process.
+pub fn needs_stream(format: OutputFormat) -> bool {
+    format == OutputFormat::I3bar
+}
+
+/// Maps a change in percent linearly onto Waybar's 0–100 `percentage` scale,
+/// clamped at the configured bounds.
+fn waybar_percentage(change_pct: f64, waybar: &WaybarConfig) -> u8 {
+    let span = waybar.percentage_max - waybar.percentage_min;
+    let scaled = (change_pct - waybar.percentage_min) / span * 100.0;
+    scaled.clamp(0.0, 100.0).round() as u8
+}
+
+/// Builds the Waybar JSON of an entry. `percentage` is left out when the change is
+/// unknown, so Waybar falls back to the first of its `format-icons`.
+fn waybar_entry(entry: &Rendered, waybar: &WaybarConfig) -> String {
+    let alt = match waybar.alt {
+        WaybarAlt::Class => entry.class(),
+        WaybarAlt::Symbol => entry.id.clone(),
+    };
+    let mut json = json!({
+        "text": entry.text,
+        "alt": alt,
+        "tooltip": entry.tooltip,
+        "class": entry.class(),
+    });
+    if let Some(change_pct) = entry.change_pct {
+        json["percentage"] = json!(waybar_percentage(change_pct, waybar));
+    }
+    json.to_string()
+}
+
+/// Builds the output line for a rendered entry. Waybar gets the text, alt, tooltip,
+/// class and percentage; other bars get the text in the class color, and i3bar
+/// blocks are marked urgent on "critdown".
+pub fn format_entry(entry: &Rendered, format: OutputFormat, config: &Config) -> String {
+    let color = color_for_class(entry.classification, config);
+    match format {
+        OutputFormat::Waybar => waybar_entry(entry, &config.waybar),
+        OutputFormat::I3bar => format!(
+            "[{}],",
+            json!({
+                "name": "stocker",
+                "full_text": entry.text,
+                "short_text": entry.short_text,
+                "color": color,
+                "urgent": entry.classification == "critdown",
+            })
+        ),
+        _ => render_segment(
+            &Segment {
+                text: entry.text.clone(),
+                color: Some(color),
+            },
+            format,
+        ),
+    }
+}
+
+/// Builds the output line for the visible window of the ticker.
+pub fn format_ticker(window: &[Segment], tooltip: &str, format: OutputFormat) -> String {
+    let text = render_segments(window, format);
+    match format {
+        OutputFormat::Waybar => json!({
+            "text": text,
+            "tooltip": tooltip,
+            "class": "ticker",
+        })
+        .to_string(),
+        // Item colors are carried by the Pango markup of the window.
+        OutputFormat::I3bar => format!(
+            "[{}],",
+            json!({
+                "name": "stocker-ticker",
+                "full_text": text,
+                "markup": "pango",
+            })
+        ),
+        _ => text,
+    }
+}
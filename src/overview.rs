@@ -0,0 +1,165 @@
+use unicode_width::UnicodeWidthStr;
+
+use crate::config::{Config, Instrument};
+use crate::output::{color_for_class, escape_pango};
+use crate::portfolio::{self, Summary};
+use crate::quote::{self, Quote};
+
+const HEADER: [&str; 8] = [
+    "", "Name", "Price", "Change", "High", "Low", "Age", "Source",
+];
+
+/// Columns holding numbers, which are aligned to the right.
+const RIGHT_ALIGNED: [bool; 8] = [false, false, true, true, true, true, true, false];
+
+/// One line of the table, in the color of its classification.
+struct Row {
+    cells: [String; 8],
+    color: Option<String>,
+}
+
+/// Formats a data age compactly, e.g. "45s", "12m", "3h" or "2d".
+fn format_age(seconds: u64) -> String {
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m", seconds / 60),
+        3600..=86_399 => format!("{}h", seconds / 3600),
+        _ => format!("{}d", seconds / 86_400),
+    }
+}
+
+/// The label or symbol of an instrument, after its icon if it has one.
+fn row_name(instrument: &Instrument) -> String {
+    let name = if instrument.label.is_empty() {
+        &instrument.symbol
+    } else {
+        &instrument.label
+    };
+    if instrument.icon.is_empty() {
+        name.clone()
+    } else {
+        format!("{} {}", instrument.icon, name)
+    }
+}
+
+fn instrument_row(instrument: &Instrument, quote: Option<&Quote>, config: &Config) -> Row {
+    let name = row_name(instrument);
+    let quote = match quote {
+        Some(quote) => quote,
+        None => {
+            return Row {
+                cells: [
+                    String::new(),
+                    name,
+                    "unavailable".to_string(),
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                ],
+                color: None,
+            }
+        }
+    };
+    let money = |amount: Option<f64>| match amount {
+        Some(amount) => quote::format_money(amount, &quote.currency, instrument, config),
+        None => "–".to_string(),
+    };
+    let mut age = format_age(quote.age());
+    if quote.stale {
+        age.push_str(" (stale)");
+    }
+    let class = quote::classify(quote.change_pct(), &config.thresholds);
+    Row {
+        cells: [
+            String::new(),
+            name,
+            quote::format_price(quote, instrument, config),
+            quote::format_change(quote.change_pct(), config),
+            money(quote.high),
+            money(quote.low),
+            age,
+            quote.source.to_string(),
+        ],
+        color: Some(color_for_class(class, config)),
+    }
+}
+
+fn portfolio_row(summary: &Summary, config: &Config) -> Row {
+    let class = quote::classify(summary.change_pct(), &config.thresholds);
+    Row {
+        cells: [
+            String::new(),
+            config.portfolio.label.clone(),
+            portfolio::money(summary.value, summary, config),
+            quote::format_change(summary.change_pct(), config),
+            String::new(),
+            String::new(),
+            String::new(),
+            String::new(),
+        ],
+        color: Some(color_for_class(class, config)),
+    }
+}
+
+/// Pads every cell to the width of its column and joins them into one line.
+fn layout(cells: &[String; 8], widths: &[usize; 8]) -> String {
+    let columns: Vec<String> = cells
+        .iter()
+        .zip(widths)
+        .zip(RIGHT_ALIGNED)
+        .filter(|((_, width), _)| **width > 0)
+        .map(|((cell, width), right)| {
+            let padding = " ".repeat(width - cell.width());
+            if right {
+                format!("{}{}", padding, cell)
+            } else {
+                format!("{}{}", cell, padding)
+            }
+        })
+        .collect();
+    columns.join("  ").trim_end().to_string()
+}
+
+/// Builds a Pango tooltip with one line per instrument (and the portfolio entry, if
+/// given), each in its class color. Quotes that could not be fetched are listed as
+/// unavailable. `current` marks the line of the entry shown in the bar.
+pub fn tooltip(
+    quotes: &[(&Instrument, Option<Quote>)],
+    portfolio: Option<&Summary>,
+    current: Option<usize>,
+    config: &Config,
+) -> String {
+    let mut rows: Vec<Row> = quotes
+        .iter()
+        .map(|(instrument, quote)| instrument_row(instrument, quote.as_ref(), config))
+        .collect();
+    if let Some(summary) = portfolio {
+        rows.push(portfolio_row(summary, config));
+    }
+    if let Some(row) = current.and_then(|index| rows.get_mut(index)) {
+        row.cells[0] = "▸".to_string();
+    }
+
+    let header = HEADER.map(|title| title.to_string());
+    let mut widths = [0; 8];
+    for cells in std::iter::once(&header).chain(rows.iter().map(|row| &row.cells)) {
+        for (width, cell) in widths.iter_mut().zip(cells) {
+            *width = (*width).max(cell.width());
+        }
+    }
+
+    let mut lines = vec![format!(
+        "<b>{}</b>",
+        escape_pango(&layout(&header, &widths))
+    )];
+    for row in &rows {
+        let line = escape_pango(&layout(&row.cells, &widths));
+        lines.push(match &row.color {
+            Some(color) => format!("<span color='{}'>{}</span>", color, line),
+            None => line,
+        });
+    }
+    format!("<tt>{}</tt>", lines.join("\n"))
+}
@@ -0,0 +1,82 @@
+use chrono::prelude::*;
+use chrono::{Duration, Months};
+
+use crate::config::{Instrument, PerformanceConfig, Period};
+use crate::quote::{calculate_percentage_change, Quote, QuoteProvider};
+
+/// Closing price of one trading day.
+#[derive(Debug, Clone, Copy)]
+pub struct DailyClose {
+    pub date: NaiveDate,
+    pub close: f64,
+}
+
+/// Change of a quote over one of the configured periods.
+#[derive(Debug, Clone, Copy)]
+pub struct PeriodChange {
+    pub period: Period,
+    /// `None` if the daily prices don't reach back to the start of the period.
+    pub change_pct: Option<f64>,
+}
+
+/// The day whose close the change over `period` is measured against.
+/// Year-to-date changes start from the last close of the previous year.
+fn period_start(period: Period, today: NaiveDate) -> NaiveDate {
+    match period {
+        Period::Week => today - Duration::days(7),
+        Period::Month => today.checked_sub_months(Months::new(1)).unwrap_or(today),
+        Period::YearToDate => NaiveDate::from_ymd_opt(today.year() - 1, 12, 31).unwrap_or(today),
+        Period::Year => today.checked_sub_months(Months::new(12)).unwrap_or(today),
+    }
+}
+
+/// The close of the last trading day on or before `date`, from closes sorted oldest first.
+fn close_on_or_before(closes: &[DailyClose], date: NaiveDate) -> Option<f64> {
+    closes
+        .iter()
+        .take_while(|day| day.date <= date)
+        .last()
+        .map(|day| day.close)
+}
+
+/// Computes the change of `price` over each period from a list of daily closes.
+pub fn changes(
+    price: f64,
+    closes: &[DailyClose],
+    periods: &[Period],
+    today: NaiveDate,
+) -> Vec<PeriodChange> {
+    periods
+        .iter()
+        .map(|&period| PeriodChange {
+            period,
+            change_pct: close_on_or_before(closes, period_start(period, today))
+                .and_then(|reference| calculate_percentage_change(price, reference)),
+        })
+        .collect()
+}
+
+/// Fills in the quote's performance over the configured periods. If the daily prices
+/// can't be fetched, every period is shown as unknown.
+pub fn attach(
+    quote: &mut Quote,
+    provider: &dyn QuoteProvider,
+    instrument: &Instrument,
+    config: &PerformanceConfig,
+) {
+    if config.periods.is_empty() {
+        return;
+    }
+    let closes = provider
+        .daily_closes(instrument, config.cache_max_age)
+        .unwrap_or_else(|e| {
+            eprintln!("Could not fetch daily prices for {}: {}", quote.symbol, e);
+            Vec::new()
+        });
+    quote.performance = changes(
+        quote.price,
+        &closes,
+        &config.periods,
+        Utc::now().date_naive(),
+    );
+}
@@ -0,0 +1,236 @@
+use reqwest::blocking::Client;
+
+use crate::config::{Config, Instrument};
+use crate::currency;
+use crate::fx;
+use crate::number;
+use crate::quote::{self, calculate_percentage_change, Quote, Rendered};
+
+/// A holding's value and profit, in the currency of its quote.
+#[derive(Debug, Clone, Copy)]
+pub struct Position {
+    pub quantity: f64,
+    pub value: f64,
+    /// Change in value since the reference price, if one is known.
+    pub day_change: Option<f64>,
+    /// Value minus cost, if a cost basis is configured.
+    pub gain: Option<f64>,
+    pub gain_pct: Option<f64>,
+}
+
+impl Position {
+    /// The position held in `instrument`, if it has a quantity.
+    pub fn of(quote: &Quote, instrument: &Instrument) -> Option<Position> {
+        let quantity = instrument.quantity?;
+        // The cost basis is given in the instrument's own currency.
+        let rate = quote.converted_from.as_ref().map_or(1.0, |c| c.rate);
+        let value = quantity * quote.price;
+        let cost = instrument.cost_basis.map(|cost| cost * quantity * rate);
+        Some(Position {
+            quantity,
+            value,
+            day_change: quote
+                .reference
+                .map(|reference| quantity * (quote.price - reference)),
+            gain: cost.map(|cost| value - cost),
+            gain_pct: cost.and_then(|cost| calculate_percentage_change(value, cost)),
+        })
+    }
+}
+
+/// One holding of the portfolio, converted into the portfolio currency.
+#[derive(Debug, Clone)]
+struct Holding {
+    name: String,
+    value: f64,
+    day_change: Option<f64>,
+}
+
+/// Totals over all holdings, in one currency.
+#[derive(Debug, Clone)]
+pub struct Summary {
+    pub currency: String,
+    pub value: f64,
+    /// Holdings without a reference price count as unchanged.
+    pub day_change: f64,
+    /// Gain of the holdings with a cost basis, and what they cost.
+    pub gain: Option<f64>,
+    pub cost: f64,
+    pub stale: bool,
+    holdings: Vec<Holding>,
+    /// Holdings left out because they couldn't be converted.
+    excluded: Vec<String>,
+}
+
+impl Summary {
+    /// Day change in percent of the value at the reference prices.
+    pub fn change_pct(&self) -> Option<f64> {
+        calculate_percentage_change(self.value, self.value - self.day_change)
+    }
+
+    pub fn gain_pct(&self) -> Option<f64> {
+        self.gain
+            .and_then(|gain| calculate_percentage_change(self.cost + gain, self.cost))
+    }
+}
+
+/// Whether the portfolio entry is shown for these instruments.
+pub fn shows(config: &Config, instruments: &[&Instrument]) -> bool {
+    config.portfolio.show && instruments.iter().any(|i| i.quantity.is_some())
+}
+
+/// Sums up the positions of the given quotes in the portfolio currency. Returns `None`
+/// if none of the quotes belong to a holding.
+pub fn summarize(
+    quotes: &[(&Instrument, Quote)],
+    config: &Config,
+    client: &Client,
+) -> Option<Summary> {
+    let positions: Vec<(&Instrument, &Quote, Position)> = quotes
+        .iter()
+        .filter_map(|(instrument, quote)| {
+            Position::of(quote, instrument).map(|position| (*instrument, quote, position))
+        })
+        .collect();
+    let currency = config
+        .portfolio
+        .currency
+        .as_ref()
+        .or(config.fx.display_currency.as_ref())
+        .map(|code| currency::normalize_code(code))
+        .unwrap_or_else(|| {
+            positions
+                .first()
+                .map(|(_, q, _)| q.currency.clone())
+                .unwrap_or_default()
+        });
+
+    let mut summary = Summary {
+        currency,
+        value: 0.0,
+        day_change: 0.0,
+        gain: None,
+        cost: 0.0,
+        stale: false,
+        holdings: Vec::new(),
+        excluded: Vec::new(),
+    };
+    for (instrument, quote, position) in &positions {
+        let rate = match fx::rate(&quote.currency, &summary.currency, config, client) {
+            Ok(rate) => rate,
+            Err(e) => {
+                eprintln!(
+                    "Leaving {} out of the portfolio: no {} to {} rate: {}",
+                    quote.symbol, quote.currency, summary.currency, e
+                );
+                summary.excluded.push(instrument.display_name().to_string());
+                continue;
+            }
+        };
+        summary.value += position.value * rate;
+        summary.day_change += position.day_change.unwrap_or(0.0) * rate;
+        if let Some(gain) = position.gain {
+            summary.gain = Some(summary.gain.unwrap_or(0.0) + gain * rate);
+            summary.cost += (position.value - gain) * rate;
+        }
+        summary.stale |= quote.stale;
+        summary.holdings.push(Holding {
+            name: instrument.display_name().to_string(),
+            value: position.value * rate,
+            day_change: position.day_change.map(|change| change * rate),
+        });
+    }
+
+    if summary.holdings.is_empty() && summary.excluded.is_empty() {
+        None
+    } else {
+        Some(summary)
+    }
+}
+
+/// Formats an amount in the portfolio currency with the `[format]` settings.
+pub fn money(amount: f64, summary: &Summary, config: &Config) -> String {
+    quote::format_money_with(
+        amount,
+        &summary.currency,
+        config.format.decimals,
+        config.format.compact,
+        config,
+    )
+}
+
+/// Builds the one-line summary, e.g. "Portfolio $12,345.67 (1.23%)".
+pub fn format_text(summary: &Summary, config: &Config) -> String {
+    format!(
+        "{} {} ({})",
+        config.portfolio.label,
+        money(summary.value, summary, config),
+        quote::format_change(summary.change_pct(), config)
+    )
+}
+
+/// Builds the tooltip with the totals and the value of each holding.
+pub fn format_tooltip(summary: &Summary, config: &Config) -> String {
+    let mut tooltip = format!(
+        "{}\nToday: {}",
+        format_text(summary, config),
+        quote::signed(
+            summary.day_change,
+            money(summary.day_change, summary, config)
+        )
+    );
+    if let Some(gain) = summary.gain {
+        tooltip.push_str(&format!(
+            " | Unrealized: {} ({})",
+            quote::signed(gain, money(gain, summary, config)),
+            quote::format_change(summary.gain_pct(), config)
+        ));
+    }
+    for holding in &summary.holdings {
+        tooltip.push_str(&format!(
+            "\n{} {}",
+            holding.name,
+            money(holding.value, summary, config)
+        ));
+        if let Some(change) = holding.day_change {
+            tooltip.push_str(&format!(
+                " (today {})",
+                quote::signed(change, money(change, summary, config))
+            ));
+        }
+    }
+    if !summary.excluded.is_empty() {
+        tooltip.push_str(&format!(
+            "\nNot included (no exchange rate): {}",
+            summary.excluded.join(", ")
+        ));
+    }
+    tooltip
+}
+
+/// Renders the portfolio entry, classified by its day change.
+pub fn render(summary: &Summary, config: &Config) -> Rendered {
+    Rendered {
+        text: format_text(summary, config),
+        short_text: format!(
+            "{} {}",
+            config.portfolio.label,
+            quote::format_change(summary.change_pct(), config)
+        ),
+        tooltip: format_tooltip(summary, config),
+        classification: quote::classify(summary.change_pct(), &config.thresholds),
+        stale: summary.stale,
+        id: "portfolio".to_string(),
+        change_pct: summary.change_pct(),
+    }
+}
+
+/// Formats a quantity with as many decimals as needed, e.g. "10" or "0.25".
+pub fn format_quantity(quantity: f64, config: &Config) -> String {
+    let formatted = format!("{:.8}", quantity);
+    let decimals = formatted
+        .split_once('.')
+        .map(|(_, fraction)| fraction.trim_end_matches('0').len())
+        .unwrap_or(0);
+    number::format_number(quantity, decimals, quote::number_style(config))
+}
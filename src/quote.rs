@@ -0,0 +1,531 @@
+use chrono::prelude::*;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::{Config, Decimals, Instrument, Period, Thresholds};
+use crate::currency;
+use crate::number::{self, NumberStyle};
+use crate::performance::{DailyClose, PeriodChange};
+use crate::portfolio::{self, Position};
+use crate::template;
+
+/// A single price observation returned by a [`QuoteProvider`].
+#[derive(Debug, Clone)]
+pub struct Quote {
+    pub symbol: String,
+    pub price: f64,
+    /// Price the change is measured against (previous close for stocks, ~24h ago for crypto).
+    pub reference: Option<f64>,
+    /// Unix timestamp the reference price was taken at, if the provider reports it.
+    pub reference_time: Option<u64>,
+    /// Session high and low (the last 24 hours for crypto), in the quote currency.
+    pub high: Option<f64>,
+    pub low: Option<f64>,
+    /// Traded volume over the same period, in units of the instrument.
+    pub volume: Option<f64>,
+    /// ISO 4217 code of the currency `price` is quoted in.
+    pub currency: String,
+    /// Unix timestamp (seconds) at which the underlying data was fetched.
+    pub timestamp: u64,
+    /// Short name of the data source, e.g. "tiingo".
+    pub source: &'static str,
+    /// Maximum age (in seconds) the cache allowed for this data.
+    pub max_age: u64,
+    /// Set when fetching failed and the data comes from an expired cache.
+    pub stale: bool,
+    /// Unix timestamp of the next session open while the instrument's market is closed.
+    pub market_reopens: Option<u64>,
+    /// The price as quoted by the provider, if it was converted into the display currency.
+    pub converted_from: Option<Conversion>,
+    /// Changes over the periods configured in `[performance]`.
+    pub performance: Vec<PeriodChange>,
+}
+
+/// Original price and exchange rate of a quote converted into another currency.
+#[derive(Debug, Clone)]
+pub struct Conversion {
+    pub price: f64,
+    pub currency: String,
+    /// Units of the display currency per unit of `currency`.
+    pub rate: f64,
+}
+
+impl Quote {
+    /// Percentage change of `price` against `reference`, if one is known.
+    pub fn change_pct(&self) -> Option<f64> {
+        self.reference
+            .and_then(|reference| calculate_percentage_change(self.price, reference))
+    }
+
+    /// Seconds elapsed since the data was fetched.
+    pub fn age(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|now| now.as_secs().saturating_sub(self.timestamp))
+            .unwrap_or(0)
+    }
+}
+
+/// A source of market data. Adding a new data source only requires implementing this trait;
+/// classification and formatting are shared by all providers.
+pub trait QuoteProvider {
+    /// Fetches the latest quote for `instrument`, using the on-disk cache where possible.
+    fn fetch(&self, instrument: &Instrument) -> Result<Quote, Box<dyn std::error::Error>>;
+
+    /// Fetches daily closing prices covering at least the past year, oldest first,
+    /// cached for `max_age` seconds. Used for the `[performance]` periods.
+    fn daily_closes(
+        &self,
+        instrument: &Instrument,
+        max_age: u64,
+    ) -> Result<Vec<DailyClose>, Box<dyn std::error::Error>>;
+
+    /// Fetches the data of several instruments in one request where the API allows it
+    /// and stores it in their caches, so the following `fetch` calls are answered from
+    /// the cache. Providers without batch requests keep the default, which does nothing.
+    fn prefetch(&self, _instruments: &[&Instrument]) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+}
+
+/// Calculates percentage change between two values.
+/// Returns None if the base value is zero to avoid division by zero.
+pub fn calculate_percentage_change(current: f64, base: f64) -> Option<f64> {
+    if base == 0.0 {
+        None
+    } else {
+        Some(((current - base) / base) * 100.0)
+    }
+}
+
+/// Classifies a percentage change using the global thresholds.
+/// An unknown change is classified as "up".
+pub fn classify(change_pct: Option<f64>, thresholds: &Thresholds) -> &'static str {
+    match change_pct {
+        Some(change) if change < thresholds.down => {
+            if change < thresholds.critdown {
+                "critdown"
+            } else {
+                "down"
+            }
+        }
+        Some(change) if change > thresholds.wayup => "wayup",
+        _ => "up",
+    }
+}
+
+/// Separators for prices and changes, from the `[format]` settings and locale.
+pub fn number_style(config: &Config) -> NumberStyle {
+    let locale = &config.format.number_locale;
+    NumberStyle {
+        decimal_separator: locale.decimal_separator,
+        group_separator: config
+            .format
+            .thousands_separator
+            .then_some(locale.group_separator),
+    }
+}
+
+/// Formats an amount of money with the instrument's decimals and compact setting
+/// (falling back to `[format]`) and attaches its currency, e.g. "$712,345.67".
+pub fn format_money(
+    amount: f64,
+    currency: &str,
+    instrument: &Instrument,
+    config: &Config,
+) -> String {
+    format_money_with(
+        amount,
+        currency,
+        instrument.decimals.unwrap_or(config.format.decimals),
+        instrument.compact.unwrap_or(config.format.compact),
+        config,
+    )
+}
+
+/// Formats an amount of money with the given decimals and compact setting.
+pub fn format_money_with(
+    amount: f64,
+    currency: &str,
+    decimals: Decimals,
+    compact: bool,
+    config: &Config,
+) -> String {
+    let format = &config.format;
+    let number = number::format_price(
+        amount,
+        decimals,
+        format.significant_figures,
+        compact,
+        number_style(config),
+    );
+    currency::format_amount(
+        &number,
+        currency,
+        format.currency_display,
+        format.number_locale.currency_placement,
+    )
+}
+
+/// Puts a "+" in front of a formatted gain, e.g. "+$34.50".
+pub fn signed(amount: f64, formatted: String) -> String {
+    if amount > 0.0 {
+        format!("+{}", formatted)
+    } else {
+        formatted
+    }
+}
+
+/// Formats the price of a quote in its currency, e.g. "$123.45".
+pub fn format_price(quote: &Quote, instrument: &Instrument, config: &Config) -> String {
+    format_money(quote.price, &quote.currency, instrument, config)
+}
+
+/// Formats the percentage change with two decimals and a percent sign placed
+/// as the locale expects ("2.34%", "2,34 %"), or "NA" if it is unknown.
+pub fn format_change(change_pct: Option<f64>, config: &Config) -> String {
+    format_percent(change_pct, 2, config)
+}
+
+/// Formats a percentage with the given number of decimals, or "NA" if it is unknown.
+fn format_percent(value: Option<f64>, decimals: usize, config: &Config) -> String {
+    let Some(value) = value else {
+        return "NA".to_string();
+    };
+    let number = number::format_number(value, decimals, number_style(config));
+    if config.format.number_locale.percent_spaced {
+        format!("{} %", number)
+    } else {
+        format!("{}%", number)
+    }
+}
+
+/// Template used for the bar text when none is configured.
+const DEFAULT_TEXT_TEMPLATE: &str = "{name} {price} ({change_pct})";
+
+/// Placeholders available in `text`, `tooltip` and `ticker` templates.
+pub const PLACEHOLDERS: &[&str] = &[
+    "symbol",
+    "label",
+    "icon",
+    "name",
+    "source",
+    "currency",
+    "currency_symbol",
+    "price",
+    "reference",
+    "change",
+    "change_pct",
+    "arrow",
+    "high",
+    "low",
+    "volume",
+    "age",
+    "max_age",
+    "change_1w",
+    "change_1m",
+    "change_ytd",
+    "change_1y",
+    "quantity",
+    "value",
+    "day_pnl",
+    "gain",
+    "gain_pct",
+];
+
+/// Formats the placeholder `name` for a quote. A precision sets the decimals of
+/// numbers and truncates text. Values that aren't known are shown as "NA".
+fn placeholder(
+    name: &str,
+    precision: Option<usize>,
+    quote: &Quote,
+    instrument: &Instrument,
+    config: &Config,
+) -> Option<String> {
+    let text = |value: &str| match precision {
+        Some(max) => value.chars().take(max).collect(),
+        None => value.to_string(),
+    };
+    let money = |amount: Option<f64>| match amount {
+        Some(amount) => match precision {
+            Some(decimals) => format_money_with(
+                amount,
+                &quote.currency,
+                Decimals::Fixed(decimals),
+                false,
+                config,
+            ),
+            None => format_money(amount, &quote.currency, instrument, config),
+        },
+        None => "NA".to_string(),
+    };
+    let signed_money = |amount: Option<f64>| match amount {
+        Some(amount) => signed(amount, money(Some(amount))),
+        None => "NA".to_string(),
+    };
+    let percent = |value: Option<f64>| format_percent(value, precision.unwrap_or(2), config);
+    let period = |period: Period| {
+        percent(
+            quote
+                .performance
+                .iter()
+                .find(|change| change.period == period)
+                .and_then(|change| change.change_pct),
+        )
+    };
+    let position = Position::of(quote, instrument);
+
+    Some(match name {
+        "symbol" => text(&quote.symbol),
+        "label" => text(if instrument.label.is_empty() {
+            &instrument.symbol
+        } else {
+            &instrument.label
+        }),
+        "icon" => text(&instrument.icon),
+        "name" => text(instrument.display_name()),
+        "source" => text(quote.source),
+        "currency" => text(&quote.currency),
+        "currency_symbol" => {
+            let symbol = currency::currency_info(&quote.currency).symbol;
+            text(if symbol.is_empty() {
+                &quote.currency
+            } else {
+                symbol
+            })
+        }
+        "price" => money(Some(quote.price)),
+        "reference" => money(quote.reference),
+        "change" => signed_money(quote.reference.map(|reference| quote.price - reference)),
+        "change_pct" => percent(quote.change_pct()),
+        "arrow" => match quote.change_pct() {
+            Some(change) if change > 0.0 => "▲",
+            Some(change) if change < 0.0 => "▼",
+            _ => "–",
+        }
+        .to_string(),
+        "high" => money(quote.high),
+        "low" => money(quote.low),
+        "volume" => match quote.volume {
+            // Whole units for large volumes, two decimals for small crypto volumes.
+            Some(volume) => {
+                let default = if volume >= 1000.0 { 0 } else { 2 };
+                number::format_number(volume, precision.unwrap_or(default), number_style(config))
+            }
+            None => "NA".to_string(),
+        },
+        "age" => quote.age().to_string(),
+        "max_age" => quote.max_age.to_string(),
+        "change_1w" => period(Period::Week),
+        "change_1m" => period(Period::Month),
+        "change_ytd" => period(Period::YearToDate),
+        "change_1y" => period(Period::Year),
+        "quantity" => match position {
+            Some(position) => match precision {
+                Some(decimals) => {
+                    number::format_number(position.quantity, decimals, number_style(config))
+                }
+                None => portfolio::format_quantity(position.quantity, config),
+            },
+            None => "NA".to_string(),
+        },
+        "value" => money(position.map(|p| p.value)),
+        "day_pnl" => signed_money(position.and_then(|p| p.day_change)),
+        "gain" => signed_money(position.and_then(|p| p.gain)),
+        "gain_pct" => percent(position.and_then(|p| p.gain_pct)),
+        _ => return None,
+    })
+}
+
+/// Renders a template with the placeholders of a quote.
+fn render_template(
+    template: &str,
+    quote: &Quote,
+    instrument: &Instrument,
+    config: &Config,
+) -> String {
+    template::render(template, |name, precision| {
+        placeholder(name, precision, quote, instrument, config)
+    })
+}
+
+/// Builds the one-line summary shown in the bar from the instrument's or the
+/// `[format]` text template, e.g. "NVDA $123.45 (2.34%)".
+pub fn format_text(quote: &Quote, instrument: &Instrument, config: &Config) -> String {
+    let template = instrument
+        .text
+        .as_deref()
+        .or(config.format.text.as_deref())
+        .unwrap_or(DEFAULT_TEXT_TEMPLATE);
+    render_template(template, quote, instrument, config)
+}
+
+/// Builds the text of a ticker item: the `ticker` template if one is configured,
+/// otherwise the bar text.
+pub fn format_ticker_text(quote: &Quote, instrument: &Instrument, config: &Config) -> String {
+    match instrument
+        .ticker
+        .as_deref()
+        .or(config.format.ticker.as_deref())
+    {
+        Some(template) => render_template(template, quote, instrument, config),
+        None => format_text(quote, instrument, config),
+    }
+}
+
+/// Builds the tooltip from the configured template, or the built-in tooltip with the
+/// price, the data source and the age of the underlying data.
+pub fn format_tooltip(quote: &Quote, instrument: &Instrument, config: &Config) -> String {
+    if let Some(template) = instrument
+        .tooltip
+        .as_deref()
+        .or(config.format.tooltip.as_deref())
+    {
+        return render_template(template, quote, instrument, config);
+    }
+
+    let mut tooltip = format!(
+        "{} {} ({}) via {}\n",
+        quote.symbol,
+        format_price(quote, instrument, config),
+        format_change(quote.change_pct(), config),
+        quote.source
+    );
+    if let Some(original) = &quote.converted_from {
+        tooltip.push_str(&format!(
+            "Original: {} (1 {} = {} {})\n",
+            format_money(original.price, &original.currency, instrument, config),
+            original.currency,
+            number::format_number(original.rate, 4, number_style(config)),
+            quote.currency
+        ));
+    }
+    if let Some(position) = Position::of(quote, instrument) {
+        tooltip.push_str(&format!(
+            "Position: {} × {} = {}\n",
+            portfolio::format_quantity(position.quantity, config),
+            format_price(quote, instrument, config),
+            format_money(position.value, &quote.currency, instrument, config)
+        ));
+        let mut profit = Vec::new();
+        if let Some(change) = position.day_change {
+            profit.push(format!(
+                "Today: {}",
+                signed(
+                    change,
+                    format_money(change, &quote.currency, instrument, config)
+                )
+            ));
+        }
+        if let Some(gain) = position.gain {
+            profit.push(format!(
+                "Unrealized: {} ({})",
+                signed(
+                    gain,
+                    format_money(gain, &quote.currency, instrument, config)
+                ),
+                format_change(position.gain_pct, config)
+            ));
+        }
+        if !profit.is_empty() {
+            tooltip.push_str(&format!("{}\n", profit.join(" | ")));
+        }
+    }
+    if let Some(at) = quote
+        .reference_time
+        .and_then(|ts| Local.timestamp_opt(ts as i64, 0).single())
+    {
+        match quote.reference {
+            Some(reference) => tooltip.push_str(&format!(
+                "Reference: {} at {}\n",
+                format_money(reference, &quote.currency, instrument, config),
+                at.format("%a %H:%M")
+            )),
+            None => tooltip.push_str(&format!(
+                "Reference: no price available for {}\n",
+                at.format("%a %H:%M")
+            )),
+        }
+    }
+    if !quote.performance.is_empty() {
+        let periods: Vec<String> = quote
+            .performance
+            .iter()
+            .map(|change| {
+                format!(
+                    "{} {}",
+                    change.period.label(),
+                    format_change(change.change_pct, config)
+                )
+            })
+            .collect();
+        tooltip.push_str(&format!("Performance: {}\n", periods.join(" | ")));
+    }
+    match quote
+        .market_reopens
+        .and_then(|ts| Local.timestamp_opt(ts as i64, 0).single())
+    {
+        Some(reopens) => tooltip.push_str(&format!(
+            "Cache Age: {} seconds (Market closed until {})",
+            quote.age(),
+            reopens.format("%a %H:%M")
+        )),
+        None => tooltip.push_str(&format!(
+            "Cache Age: {} seconds (Max allowed: {} seconds)",
+            quote.age(),
+            quote.max_age
+        )),
+    }
+    if quote.stale {
+        tooltip.push_str(&format!(
+            "\nStale: {} unreachable, showing data from {} seconds ago",
+            quote.source,
+            quote.age()
+        ));
+    }
+    tooltip
+}
+
+/// A quote rendered for output, independent of the bar protocol.
+#[derive(Debug, Clone)]
+pub struct Rendered {
+    pub text: String,
+    /// Compact variant of `text` for bars that shorten blocks when space runs out.
+    pub short_text: String,
+    pub tooltip: String,
+    /// Classification of the change, see [`classify`].
+    pub classification: &'static str,
+    /// Set when the data comes from an expired cache.
+    pub stale: bool,
+    /// Instrument symbol, or "portfolio" for the portfolio entry.
+    pub id: String,
+    /// Change in percent the classification is based on.
+    pub change_pct: Option<f64>,
+}
+
+impl Rendered {
+    /// The CSS class: the classification, with a "-stale" suffix when the data
+    /// comes from an expired cache.
+    pub fn class(&self) -> String {
+        if self.stale {
+            format!("{}-stale", self.classification)
+        } else {
+            self.classification.to_string()
+        }
+    }
+}
+
+/// Template of the compact variant of the bar text.
+const SHORT_TEXT_TEMPLATE: &str = "{name} {change_pct}";
+
+/// Renders the text, compact text, tooltip and classification of a quote.
+pub fn render(quote: &Quote, instrument: &Instrument, config: &Config) -> Rendered {
+    Rendered {
+        text: format_text(quote, instrument, config),
+        short_text: render_template(SHORT_TEXT_TEMPLATE, quote, instrument, config),
+        tooltip: format_tooltip(quote, instrument, config),
+        classification: classify(quote.change_pct(), &config.thresholds),
+        stale: quote.stale,
+        id: instrument.symbol.clone(),
+        change_pct: quote.change_pct(),
+    }
+}
@@ -0,0 +1,254 @@
+use unicode_width::UnicodeWidthStr;
+
+/// Horizontal alignment of a padded placeholder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+/// A `{name:spec}` placeholder. The spec follows Rust's format syntax in a reduced
+/// form: `[[fill]align][width][.precision]`, e.g. `{price:>12.4}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Field {
+    name: String,
+    fill: char,
+    align: Align,
+    width: usize,
+    precision: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Field(Field),
+}
+
+/// Parses the text between the braces of a placeholder.
+fn parse_field(inner: &str) -> Result<Field, String> {
+    let (name, spec) = inner.split_once(':').unwrap_or((inner, ""));
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("empty placeholder {}".to_string());
+    }
+
+    let to_align = |c: char| match c {
+        '<' => Some(Align::Left),
+        '>' => Some(Align::Right),
+        '^' => Some(Align::Center),
+        _ => None,
+    };
+    let chars: Vec<char> = spec.chars().collect();
+    let (fill, align, rest) = match chars.as_slice() {
+        [fill, align, ..] if to_align(*align).is_some() => (*fill, to_align(*align), &chars[2..]),
+        [align, ..] if to_align(*align).is_some() => (' ', to_align(*align), &chars[1..]),
+        _ => (' ', None, &chars[..]),
+    };
+    let rest: String = rest.iter().collect();
+    let (width, precision) = match rest.split_once('.') {
+        Some((width, precision)) => (width, Some(precision)),
+        None => (rest.as_str(), None),
+    };
+    let invalid = || format!("invalid format spec '{}' for {{{}}}", spec, name);
+    let width = if width.is_empty() {
+        0
+    } else {
+        width.parse().map_err(|_| invalid())?
+    };
+    let precision = match precision {
+        Some(p) => Some(p.parse().map_err(|_| invalid())?),
+        None => None,
+    };
+
+    Ok(Field {
+        name: name.to_string(),
+        fill,
+        align: align.unwrap_or(Align::Left),
+        width,
+        precision,
+    })
+}
+
+/// Splits a template into literal text and placeholders. `{{` and `}}` are literal braces.
+fn parse(template: &str) -> Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                literal.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                literal.push('}');
+            }
+            '{' => {
+                let mut inner = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some('{') => {
+                            return Err(format!("unexpected '{{' in placeholder '{{{}'", inner))
+                        }
+                        Some(c) => inner.push(c),
+                        None => return Err(format!("unclosed placeholder '{{{}'", inner)),
+                    }
+                }
+                if !literal.is_empty() {
+                    segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                }
+                segments.push(Segment::Field(parse_field(&inner)?));
+            }
+            '}' => return Err("unmatched '}' (write '}}' for a literal brace)".to_string()),
+            c => literal.push(c),
+        }
+    }
+    if !literal.is_empty() {
+        segments.push(Segment::Literal(literal));
+    }
+    Ok(segments)
+}
+
+/// Checks that a template parses and only uses the given placeholder names.
+pub fn validate(template: &str, known: &[&str]) -> Result<(), String> {
+    for segment in parse(template)? {
+        if let Segment::Field(field) = segment {
+            if !known.contains(&field.name.as_str()) {
+                return Err(format!("unknown placeholder {{{}}}", field.name));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Pads `value` to the field's width in display columns.
+fn pad(value: String, field: &Field) -> String {
+    let len = value.width();
+    if len >= field.width {
+        return value;
+    }
+    let padding = field.width - len;
+    let (before, after) = match field.align {
+        Align::Left => (0, padding),
+        Align::Right => (padding, 0),
+        Align::Center => (padding / 2, padding - padding / 2),
+    };
+    let fill = |n: usize| field.fill.to_string().repeat(n);
+    format!("{}{}{}", fill(before), value, fill(after))
+}
+
+/// Renders a template. `resolve` formats a placeholder given its name and optional
+/// precision; placeholders it doesn't know are kept as written. Templates are
+/// validated when the config is loaded, so parse errors render the template verbatim.
+pub fn render(template: &str, resolve: impl Fn(&str, Option<usize>) -> Option<String>) -> String {
+    let segments = match parse(template) {
+        Ok(segments) => segments,
+        Err(_) => return template.to_string(),
+    };
+    segments
+        .into_iter()
+        .map(|segment| match segment {
+            Segment::Literal(text) => text,
+            Segment::Field(field) => match resolve(&field.name, field.precision) {
+                Some(value) => pad(value, &field),
+                None => format!("{{{}}}", field.name),
+            },
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(
+        name: &str,
+        fill: char,
+        align: Align,
+        width: usize,
+        precision: Option<usize>,
+    ) -> Field {
+        Field {
+            name: name.to_string(),
+            fill,
+            align,
+            width,
+            precision,
+        }
+    }
+
+    fn render_name(template: &str, name: &str) -> String {
+        render(template, |placeholder, _| {
+            (placeholder == "name").then(|| name.to_string())
+        })
+    }
+
+    #[test]
+    fn specs_follow_the_format_syntax() {
+        assert_eq!(
+            parse_field("price").unwrap(),
+            field("price", ' ', Align::Left, 0, None)
+        );
+        assert_eq!(
+            parse_field("price:>12.4").unwrap(),
+            field("price", ' ', Align::Right, 12, Some(4))
+        );
+        assert_eq!(
+            parse_field("name:*^10").unwrap(),
+            field("name", '*', Align::Center, 10, None)
+        );
+        // A fill character is only taken as such when an alignment follows it.
+        assert_eq!(
+            parse_field("name:>>3").unwrap(),
+            field("name", '>', Align::Right, 3, None)
+        );
+        assert!(parse_field("name:x").is_err());
+        assert!(parse_field(":5").is_err());
+    }
+
+    #[test]
+    fn templates_split_into_literals_and_fields() {
+        assert_eq!(
+            parse("{{{symbol}}} {price:.2}").unwrap(),
+            vec![
+                Segment::Literal("{".to_string()),
+                Segment::Field(field("symbol", ' ', Align::Left, 0, None)),
+                Segment::Literal("} ".to_string()),
+                Segment::Field(field("price", ' ', Align::Left, 0, Some(2))),
+            ]
+        );
+        assert!(parse("{price").is_err());
+        assert!(parse("{pr{ice}").is_err());
+        assert!(parse("price}").is_err());
+    }
+
+    #[test]
+    fn validation_reports_unknown_placeholders() {
+        assert!(validate("{price} {change}", &["price", "change"]).is_ok());
+        assert_eq!(
+            validate("{price} {volume}", &["price"]),
+            Err("unknown placeholder {volume}".to_string())
+        );
+    }
+
+    #[test]
+    fn fields_are_padded_and_aligned() {
+        assert_eq!(render_name("[{name:<6}]", "NVDA"), "[NVDA  ]");
+        assert_eq!(render_name("[{name:>6}]", "NVDA"), "[  NVDA]");
+        assert_eq!(render_name("[{name:*^9}]", "NVDA"), "[**NVDA***]");
+        assert_eq!(render_name("[{name:3}]", "NVDA"), "[NVDA]");
+        assert_eq!(render_name("{other:5}", "NVDA"), "{other}");
+    }
+
+    #[test]
+    fn padding_counts_display_columns() {
+        // Each CJK character takes two columns.
+        assert_eq!(render_name("{name:*^10}", "株式会社"), "*株式会社*");
+        assert_eq!(render_name("{name:>6}", "日本"), "  日本");
+        // Combining accents take no column of their own.
+        assert_eq!(render_name("{name:<5}", "Cafe\u{301}"), "Cafe\u{301} ");
+    }
+}
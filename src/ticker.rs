@@ -0,0 +1,280 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::config::{ScrollDirection, StaticAlign};
+use crate::output::Segment;
+
+/// A fetched ticker: the items in their colors joined by separators, and its tooltip.
+pub struct Snapshot {
+    pub segments: Vec<Segment>,
+    pub tooltip: String,
+}
+
+/// Number of grapheme clusters in the ticker, i.e. the number of scroll positions.
+pub fn text_length(segments: &[Segment]) -> usize {
+    segments
+        .iter()
+        .map(|segment| segment.text.graphemes(true).count())
+        .sum()
+}
+
+/// Number of display columns the ticker takes up.
+pub fn text_width(segments: &[Segment]) -> usize {
+    segments.iter().map(|segment| segment.text.width()).sum()
+}
+
+/// The ticker as shown: either static, when all items fit into the window, or
+/// scrolled in a loop.
+pub struct Layout {
+    /// The items, followed by a separator when they scroll so the loop is joined.
+    pub segments: Vec<Segment>,
+    /// Number of scroll positions.
+    pub length: usize,
+    /// Item starts the ticker pauses at.
+    pub stops: Vec<usize>,
+    pub fits: bool,
+}
+
+/// Lays out the items joined by `separator` for a window of `window_size` columns.
+/// `pause` requests the item starts to stop at.
+pub fn layout(items: Vec<Segment>, separator: &str, window_size: usize, pause: bool) -> Layout {
+    let fits = text_width(&items) <= window_size;
+    let mut segments = items;
+    if !fits {
+        segments.push(Segment {
+            text: separator.to_string(),
+            color: None,
+        });
+    }
+    Layout {
+        length: text_length(&segments),
+        stops: if pause && !fits {
+            item_starts(&segments)
+        } else {
+            Vec::new()
+        },
+        segments,
+        fits,
+    }
+}
+
+/// Pads a ticker that fits into the window with spaces to `window_size` columns.
+pub fn align(segments: &[Segment], window_size: usize, align: StaticAlign) -> Vec<Segment> {
+    let free = window_size.saturating_sub(text_width(segments));
+    let (before, after) = match align {
+        StaticAlign::None => (0, 0),
+        StaticAlign::Left => (0, free),
+        StaticAlign::Center => (free / 2, free - free / 2),
+        StaticAlign::Right => (free, 0),
+    };
+    let padding = |n: usize| Segment {
+        text: " ".repeat(n),
+        color: None,
+    };
+    let mut result = Vec::with_capacity(segments.len() + 2);
+    if before > 0 {
+        result.push(padding(before));
+    }
+    result.extend_from_slice(segments);
+    if after > 0 {
+        result.push(padding(after));
+    }
+    result
+}
+
+/// Extracts `window_size` display columns starting at grapheme cluster `position`,
+/// wrapping around the end of the ticker. Clusters are never split: a double-width
+/// cluster that doesn't fit into the last column is replaced by a space, so the
+/// window always has the same width. Adjacent clusters of the same color are merged
+/// into one segment.
+pub fn window(segments: &[Segment], position: usize, window_size: usize) -> Vec<Segment> {
+    let graphemes: Vec<(&str, usize, &Option<String>)> = segments
+        .iter()
+        .flat_map(|segment| {
+            segment
+                .text
+                .graphemes(true)
+                .map(move |g| (g, g.width(), &segment.color))
+        })
+        .collect();
+    // Without any visible column the window could never be filled.
+    if graphemes.iter().all(|(_, width, _)| *width == 0) {
+        return Vec::new();
+    }
+
+    let mut result: Vec<Segment> = Vec::new();
+    let mut push = |text: &str, color: &Option<String>| match result.last_mut() {
+        Some(last) if &last.color == color => last.text.push_str(text),
+        _ => result.push(Segment {
+            text: text.to_string(),
+            color: color.clone(),
+        }),
+    };
+    let mut columns = 0;
+    let mut i = position;
+    while columns < window_size {
+        let (grapheme, width, color) = graphemes[i % graphemes.len()];
+        if columns + width > window_size {
+            push(&" ".repeat(window_size - columns), &None);
+            break;
+        }
+        push(grapheme, color);
+        columns += width;
+        i += 1;
+    }
+    result
+}
+
+/// Grapheme positions at which the items of the ticker start. The segments alternate
+/// between items and separators, as built in ticker mode.
+pub fn item_starts(segments: &[Segment]) -> Vec<usize> {
+    let mut starts = Vec::new();
+    let mut position = 0;
+    for (i, segment) in segments.iter().enumerate() {
+        if i % 2 == 0 {
+            starts.push(position);
+        }
+        position += segment.text.graphemes(true).count();
+    }
+    starts
+}
+
+/// Moves `position` by `step` grapheme clusters in `direction`, wrapping around a
+/// ticker of `length` clusters. Stops early at the first of `stops` passed on the
+/// way and reports whether it did.
+pub fn advance(
+    position: usize,
+    length: usize,
+    step: usize,
+    direction: ScrollDirection,
+    stops: &[usize],
+) -> (usize, bool) {
+    let moved = |k: usize| match direction {
+        ScrollDirection::Left => (position + k) % length,
+        ScrollDirection::Right => (position + length - k % length) % length,
+    };
+    for k in 1..=step {
+        if stops.contains(&moved(k)) {
+            return (moved(k), true);
+        }
+    }
+    (moved(step), false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(text: &str, color: Option<&str>) -> Segment {
+        Segment {
+            text: text.to_string(),
+            color: color.map(str::to_string),
+        }
+    }
+
+    fn text(segments: &[Segment]) -> String {
+        segments
+            .iter()
+            .map(|segment| segment.text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn length_counts_clusters_and_width_counts_columns() {
+        let segments = [segment("e\u{301}日", None), segment("👍🏽!", None)];
+        assert_eq!(text_length(&segments), 4);
+        assert_eq!(text_width(&segments), 6);
+    }
+
+    #[test]
+    fn window_wraps_around_the_end() {
+        let segments = [segment("ABC", None), segment(" | ", None)];
+        assert_eq!(text(&window(&segments, 4, 5)), "| ABC");
+        assert_eq!(text(&window(&segments, 0, 8)), "ABC | AB");
+    }
+
+    #[test]
+    fn window_never_splits_a_double_width_cluster() {
+        let segments = [segment("ab日本", None)];
+        assert_eq!(text(&window(&segments, 0, 4)), "ab日");
+        // "本" would need the fifth and a sixth column, so a space takes its place.
+        assert_eq!(text(&window(&segments, 0, 5)), "ab日 ");
+        assert_eq!(text(&window(&segments, 1, 4)), "b日 ");
+        assert_eq!(text(&window(&segments, 3, 4)), "本ab");
+    }
+
+    #[test]
+    fn window_keeps_combined_clusters_whole() {
+        let segments = [segment("e\u{301}x", None)];
+        assert_eq!(text(&window(&segments, 0, 2)), "e\u{301}x");
+        assert_eq!(text(&window(&segments, 1, 2)), "xe\u{301}");
+    }
+
+    #[test]
+    fn window_merges_clusters_of_one_color() {
+        let segments = [
+            segment("AB", Some("#00FF00")),
+            segment(" ", None),
+            segment("CD", Some("#FF0000")),
+            segment(" ", None),
+        ];
+        let shown = window(&segments, 1, 4);
+        assert_eq!(
+            shown,
+            vec![
+                segment("B", Some("#00FF00")),
+                segment(" ", None),
+                segment("CD", Some("#FF0000")),
+            ]
+        );
+    }
+
+    #[test]
+    fn window_of_invisible_text_is_empty() {
+        assert!(window(&[segment("\u{200b}", None)], 0, 5).is_empty());
+        assert!(window(&[], 0, 5).is_empty());
+    }
+
+    #[test]
+    fn items_start_at_every_other_segment() {
+        let segments = [
+            segment("NVDA", None),
+            segment(" | ", None),
+            segment("日本", None),
+            segment(" | ", None),
+        ];
+        assert_eq!(item_starts(&segments), vec![0, 7]);
+    }
+
+    #[test]
+    fn advance_wraps_in_both_directions() {
+        assert_eq!(advance(8, 10, 3, ScrollDirection::Left, &[]), (1, false));
+        assert_eq!(advance(1, 10, 3, ScrollDirection::Right, &[]), (8, false));
+        // Steps longer than the ticker wrap as often as needed.
+        assert_eq!(advance(1, 10, 23, ScrollDirection::Right, &[]), (8, false));
+    }
+
+    #[test]
+    fn advance_stops_at_the_first_item_start_on_the_way() {
+        let stops = [0, 7];
+        assert_eq!(advance(5, 10, 3, ScrollDirection::Left, &stops), (7, true));
+        assert_eq!(advance(8, 10, 5, ScrollDirection::Left, &stops), (0, true));
+        assert_eq!(advance(1, 10, 2, ScrollDirection::Left, &stops), (3, false));
+    }
+
+    #[test]
+    fn advance_stops_when_scrolling_right() {
+        let stops = [0, 7];
+        assert_eq!(advance(9, 10, 3, ScrollDirection::Right, &stops), (7, true));
+        assert_eq!(advance(2, 10, 4, ScrollDirection::Right, &stops), (0, true));
+        // Leaving a stop doesn't stop again at it.
+        assert_eq!(
+            advance(7, 10, 1, ScrollDirection::Right, &stops),
+            (6, false)
+        );
+        assert_eq!(
+            advance(0, 10, 2, ScrollDirection::Right, &stops),
+            (8, false)
+        );
+    }
+}
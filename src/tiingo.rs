@@ -0,0 +1,296 @@
+use chrono::prelude::*;
+use chrono::Duration;
+use reqwest::blocking::Client;
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::env;
+
+use crate::cache::{atomic_write, cache_timestamp, is_cache_valid, read_through};
+use crate::calendar::ExchangeCalendar;
+use crate::config::{ExchangeConfig, Instrument, StockConfig};
+use crate::performance::DailyClose;
+use crate::quote::{Quote, QuoteProvider};
+
+/// Days of daily prices fetched for the performance periods: a year plus a margin
+/// for weekends and holidays before the start of the 1Y period.
+const DAILY_HISTORY_DAYS: i64 = 366 + 10;
+
+/// Cache file of a ticker's IEX quote.
+fn quote_cache_file(ticker: &str) -> String {
+    format!("cache_{}.json", ticker)
+}
+
+/// How long cached Tiingo data may be used.
+struct CachePolicy {
+    max_age: u64,
+    /// Next session open while the exchange is closed.
+    reopens: Option<DateTime<Utc>>,
+}
+
+/// Stock quotes from the Tiingo IEX endpoint.
+/// The environment variable `TIINGO_API_KEY` is required.
+pub struct TiingoProvider<'a> {
+    config: &'a StockConfig,
+    exchanges: &'a HashMap<String, ExchangeConfig>,
+    client: &'a Client,
+    api_key: String,
+}
+
+impl<'a> TiingoProvider<'a> {
+    pub fn new(
+        config: &'a StockConfig,
+        exchanges: &'a HashMap<String, ExchangeConfig>,
+        client: &'a Client,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let api_key = env::var("TIINGO_API_KEY").map_err(|_| {
+            "TIINGO_API_KEY environment variable not set. Please set it with your Tiingo API key."
+        })?;
+
+        if api_key.trim().is_empty() {
+            return Err("TIINGO_API_KEY environment variable is empty".into());
+        }
+
+        Ok(TiingoProvider {
+            config,
+            exchanges,
+            client,
+            api_key,
+        })
+    }
+
+    /// Cache lifetime in effect right now, and when the market reopens if it is closed.
+    /// While the exchange is in session, and for `settle_minutes` after the close while
+    /// the closing prices settle, this is `cache_max_age`. After that, data fetched once
+    /// the prices settled stays valid until the next open.
+    fn cache_policy(
+        &self,
+        instrument: &Instrument,
+    ) -> Result<CachePolicy, Box<dyn std::error::Error>> {
+        let cache_max_age = instrument
+            .cache_max_age
+            .unwrap_or(self.config.cache_max_age);
+        let exchange = instrument
+            .exchange
+            .as_deref()
+            .unwrap_or(&self.config.exchange);
+        let calendar = ExchangeCalendar::lookup(exchange, self.exchanges)?;
+
+        let now = Utc::now();
+        if calendar.is_open(now) {
+            return Ok(CachePolicy {
+                max_age: cache_max_age,
+                reopens: None,
+            });
+        }
+        let reopens = calendar.next_open(now);
+        let settle = self.config.settle_minutes * 60;
+        let since_close = match calendar.last_close(now) {
+            Some(close) => (now - close).num_seconds().max(0) as u64,
+            None => {
+                return Ok(CachePolicy {
+                    max_age: cache_max_age,
+                    reopens,
+                })
+            }
+        };
+        Ok(CachePolicy {
+            max_age: if since_close < settle {
+                cache_max_age
+            } else {
+                // Only data fetched after the settle window counts as final.
+                since_close - settle
+            },
+            reopens,
+        })
+    }
+}
+
+impl TiingoProvider<'_> {
+    /// Sends an authenticated GET request and returns the body.
+    /// `what` describes the request in error messages.
+    fn get(&self, url: &str, what: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let response = self
+            .client
+            .get(url)
+            .header(CONTENT_TYPE, "application/json")
+            .header(AUTHORIZATION, format!("Token {}", self.api_key))
+            .send()?;
+
+        if !response.status().is_success() {
+            return Err(format!(
+                "Failed to fetch data from Tiingo for {}: HTTP status {}",
+                what,
+                response.status()
+            )
+            .into());
+        }
+
+        Ok(response.text()?)
+    }
+
+    /// Returns how many units of `to` one unit of `from` buys, from Tiingo's FX
+    /// top-of-book endpoint, cached for `max_age` seconds.
+    pub fn fx_rate(
+        &self,
+        from: &str,
+        to: &str,
+        max_age: u64,
+    ) -> Result<f64, Box<dyn std::error::Error>> {
+        let pair = format!("{}{}", from, to).to_ascii_lowercase();
+        let cache_file = format!("cache_fx_tiingo_{}.json", pair);
+        let url = format!("https://api.tiingo.com/tiingo/fx/top?tickers={}", pair);
+        let body = read_through(&cache_file, max_age, || {
+            self.get(&url, &format!("FX pair {}", pair))
+        })?;
+
+        let json_data: Value = serde_json::from_str(&body.text)?;
+        let entry = json_data
+            .get(0)
+            .ok_or_else(|| format!("Tiingo has no FX rate for {}", pair))?;
+        let field = |name: &str| entry.get(name).and_then(|v| v.as_f64());
+        field("midPrice")
+            .or_else(|| Some((field("bidPrice")? + field("askPrice")?) / 2.0))
+            .ok_or_else(|| format!("Invalid FX response for {}: {:?}", pair, entry).into())
+    }
+}
+
+impl QuoteProvider for TiingoProvider<'_> {
+    /// Fetches stock data from Tiingo for a given ticker, using caching.
+    fn fetch(&self, instrument: &Instrument) -> Result<Quote, Box<dyn std::error::Error>> {
+        let ticker = instrument.symbol.as_str();
+        let policy = self.cache_policy(instrument)?;
+        let effective_cache_max_age = policy.max_age;
+
+        let cache_file = quote_cache_file(ticker);
+        let tiingo_url = format!("https://api.tiingo.com/iex/{}", ticker);
+        let body = read_through(&cache_file, effective_cache_max_age, || {
+            self.get(&tiingo_url, &format!("ticker {}", ticker))
+        })?;
+
+        let json_data: Value = serde_json::from_str(&body.text)?;
+        let first_entry = json_data.get(0).ok_or_else(|| {
+            format!(
+                "Invalid API response for ticker {}: missing array element",
+                ticker
+            )
+        })?;
+
+        let last_price = first_entry
+            .get("tngoLast")
+            .and_then(|v| v.as_f64())
+            .ok_or_else(|| {
+                format!(
+                    "Invalid tngoLast field for ticker {}: {:?}",
+                    ticker, first_entry
+                )
+            })?;
+
+        let prev_close = first_entry
+            .get("prevClose")
+            .and_then(|v| v.as_f64())
+            .ok_or_else(|| {
+                format!(
+                    "Invalid prevClose field for ticker {}: {:?}",
+                    ticker, first_entry
+                )
+            })?;
+
+        let field = |name: &str| first_entry.get(name).and_then(|v| v.as_f64());
+
+        Ok(Quote {
+            symbol: ticker.to_string(),
+            price: last_price,
+            reference: Some(prev_close),
+            high: field("high"),
+            low: field("low"),
+            volume: field("volume"),
+            // IEX only lists US securities, so quotes are in USD unless configured otherwise.
+            currency: instrument
+                .currency
+                .clone()
+                .unwrap_or_else(|| "USD".to_string()),
+            timestamp: cache_timestamp(&cache_file)?,
+            source: "Tiingo",
+            max_age: effective_cache_max_age,
+            stale: body.stale,
+            market_reopens: policy.reopens.map(|open| open.timestamp().max(0) as u64),
+            converted_from: None,
+            performance: Vec::new(),
+            reference_time: None,
+        })
+    }
+
+    /// Fetches a year of daily closes from Tiingo's end-of-day endpoint, using the
+    /// split- and dividend-adjusted close where available.
+    fn daily_closes(
+        &self,
+        instrument: &Instrument,
+        max_age: u64,
+    ) -> Result<Vec<DailyClose>, Box<dyn std::error::Error>> {
+        let ticker = instrument.symbol.as_str();
+        let start = Utc::now().date_naive() - Duration::days(DAILY_HISTORY_DAYS);
+        let cache_file = format!("cache_{}_daily.json", ticker);
+        let url = format!(
+            "https://api.tiingo.com/tiingo/daily/{}/prices?startDate={}",
+            ticker,
+            start.format("%Y-%m-%d")
+        );
+        let body = read_through(&cache_file, max_age, || {
+            self.get(&url, &format!("daily prices of {}", ticker))
+        })?;
+
+        let json_data: Value = serde_json::from_str(&body.text)?;
+        let days = json_data
+            .as_array()
+            .ok_or_else(|| format!("Invalid daily prices response for ticker {}", ticker))?;
+        Ok(days
+            .iter()
+            .filter_map(|day| {
+                let date = day.get("date")?.as_str()?.get(..10)?;
+                Some(DailyClose {
+                    date: NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?,
+                    close: day.get("adjClose").or_else(|| day.get("close"))?.as_f64()?,
+                })
+            })
+            .collect())
+    }
+
+    /// Fetches the IEX quotes of all tickers whose cache has expired in one
+    /// `/iex/?tickers=a,b,c` request and splits the response into the per-ticker
+    /// cache files, in the same form as a single-ticker response.
+    fn prefetch(&self, instruments: &[&Instrument]) -> Result<(), Box<dyn std::error::Error>> {
+        let mut tickers: Vec<&str> = Vec::new();
+        for instrument in instruments {
+            let ticker = instrument.symbol.as_str();
+            let policy = self.cache_policy(instrument)?;
+            if !is_cache_valid(&quote_cache_file(ticker), policy.max_age)
+                && !tickers.contains(&ticker)
+            {
+                tickers.push(ticker);
+            }
+        }
+        // A single ticker gains nothing from a batch.
+        if tickers.len() < 2 {
+            return Ok(());
+        }
+
+        let joined = tickers.join(",");
+        let url = format!("https://api.tiingo.com/iex/?tickers={}", joined);
+        let text = self.get(&url, &format!("tickers {}", joined))?;
+        let json_data: Value = serde_json::from_str(&text)?;
+        let entries = json_data
+            .as_array()
+            .ok_or_else(|| format!("Invalid API response for tickers {}", joined))?;
+        // Tiingo reports tickers in upper case; unknown tickers are left out and
+        // reported by `fetch`.
+        for entry in entries {
+            let reported = entry.get("ticker").and_then(|v| v.as_str()).unwrap_or("");
+            if let Some(ticker) = tickers.iter().find(|t| t.eq_ignore_ascii_case(reported)) {
+                let body = Value::Array(vec![entry.clone()]);
+                atomic_write(&quote_cache_file(ticker), &body.to_string())?;
+            }
+        }
+        Ok(())
+    }
+}